@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use tafl_rust::game::EngineSide;
+use tafl_rust::{Rules, SearchLimits};
+
+pub const USAGE: &str = "\
+Usage: tafl-rust [COMMAND] [OPTIONS]
+
+Commands:
+  play                 Play a game on the console (default)
+  analyze              Search the starting position and print the engine's move
+  perft DEPTH          Count move sequences of DEPTH moves
+  bench                Time searches from the start of every rule set
+  selfplay             Let the engine play games against itself
+
+Options:
+  --rules NAME         Rule set: ard-ri, brandubh, fetlar, copenhagen or tablut
+  --position TEXT      Start from a position in board notation
+  --position-file FILE Start from a position or game record in FILE
+  --depth N            Maximum search depth (default 9, or 5 for `bench`, if no other
+                       limit is given)
+  --movetime MS        Search each move for at most MS milliseconds
+  --clock MS           Budget each move from MS milliseconds left on the clock
+  --inc MS             Clock increment per move, used with --clock
+  --nodes N            Stop searching after N nodes
+  --multipv N          Number of best moves to score and show
+  --hash MB            Transposition table size in megabytes
+  --engine SIDE        Side the engine plays in `play`: attackers, defenders, both or none
+  --no-hints           Don't search for a recommended move on your turns in `play`
+  --save FILE          Write the game record to FILE after every move
+  --games N            Number of games for `selfplay`
+  --divide             Split perft counts by root move
+  --help               Show this message";
+
+const DEFAULT_DEPTH: u8 = 9;
+const DEFAULT_BENCH_DEPTH: u8 = 5;
+const DEFAULT_HASH_MB: usize = 16;
+const MAX_HASH_MB: usize = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    Play,
+    Analyze,
+    Perft { depth: usize, divide: bool },
+    Bench,
+    Selfplay { games: usize },
+}
+
+pub struct Options {
+    pub command: Command,
+    pub rules: Option<Rules>,
+    pub position: Option<String>,
+    pub position_file: Option<String>,
+    pub limits: SearchLimits,
+    pub multi_pv: usize,
+    pub hash_mb: usize,
+    pub engine_side: EngineSide,
+    pub hints: bool,
+    pub save: Option<String>,
+}
+
+/// Reads the command line, without the program name. `Ok(None)` means help was asked for.
+pub fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut command = None;
+    let mut perft_depth = None;
+    let mut divide = false;
+    let mut games = 1;
+    let mut options = Options {
+        command: Command::Play,
+        rules: None,
+        position: None,
+        position_file: None,
+        limits: SearchLimits::default(),
+        multi_pv: 1,
+        hash_mb: DEFAULT_HASH_MB,
+        engine_side: EngineSide::None,
+        hints: true,
+        save: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg {
+            "--help" | "-h" | "help" => return Ok(None),
+            "--rules" => {
+                let name = value()?;
+                options.rules =
+                    Some(Rules::from_name(name).ok_or(format!("Unknown rule set: {}", name))?);
+            }
+            "--position" => options.position = Some(value()?.to_string()),
+            "--position-file" => options.position_file = Some(value()?.to_string()),
+            "--depth" => options.limits.depth = Some(number(arg, value()?)?),
+            "--movetime" => options.limits.movetime = Some(millis(arg, value()?)?),
+            "--clock" => options.limits.clock = Some(millis(arg, value()?)?),
+            "--inc" => options.limits.increment = millis(arg, value()?)?,
+            "--nodes" => options.limits.nodes = Some(number(arg, value()?)?),
+            "--multipv" => options.multi_pv = number(arg, value()?)?,
+            "--hash" => {
+                options.hash_mb = number(arg, value()?)?;
+                if options.hash_mb > MAX_HASH_MB {
+                    return Err(format!("{} can be at most {} MB", arg, MAX_HASH_MB));
+                }
+            }
+            "--engine" => {
+                options.engine_side = match value()? {
+                    "attackers" => EngineSide::Attackers,
+                    "defenders" => EngineSide::Defenders,
+                    "both" => EngineSide::Both,
+                    "none" => EngineSide::None,
+                    side => return Err(format!("Unknown side: {}", side)),
+                }
+            }
+            "--no-hints" => options.hints = false,
+            "--save" => options.save = Some(value()?.to_string()),
+            "--games" => games = number(arg, value()?)?,
+            "--divide" => divide = true,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ if command.is_none() => command = Some(arg),
+            _ if command == Some("perft") && perft_depth.is_none() => {
+                perft_depth = Some(number("perft", arg)?)
+            }
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    options.command = match command.unwrap_or("play") {
+        "play" => Command::Play,
+        "analyze" => Command::Analyze,
+        "perft" => Command::Perft {
+            depth: perft_depth.ok_or("perft needs a depth")?,
+            divide,
+        },
+        "bench" => Command::Bench,
+        "selfplay" => Command::Selfplay { games },
+        other => return Err(format!("Unknown command: {}", other)),
+    };
+    if options.limits == SearchLimits::default() {
+        options.limits.depth = Some(match options.command {
+            Command::Bench => DEFAULT_BENCH_DEPTH,
+            _ => DEFAULT_DEPTH,
+        });
+    }
+    Ok(Some(options))
+}
+
+fn number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} needs a number, not {}", option, value))
+}
+
+fn millis(option: &str, value: &str) -> Result<Duration, String> {
+    number(option, value).map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args).map(|options| options.expect("help was not asked for"))
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse("").unwrap().command, Command::Play);
+        assert_eq!(parse("analyze").unwrap().command, Command::Analyze);
+        assert_eq!(parse("bench").unwrap().command, Command::Bench);
+        assert_eq!(
+            parse("perft 3 --divide").unwrap().command,
+            Command::Perft {
+                depth: 3,
+                divide: true
+            }
+        );
+        assert_eq!(
+            parse("selfplay --games 4").unwrap().command,
+            Command::Selfplay { games: 4 }
+        );
+        assert!(parse_args(&["--help".to_string()]).unwrap().is_none());
+        assert_eq!(parse("chess").err().unwrap(), "Unknown command: chess");
+        assert_eq!(parse("perft").err().unwrap(), "perft needs a depth");
+        assert_eq!(parse("perft 3 4").err().unwrap(), "Unexpected argument: 4");
+    }
+
+    #[test]
+    fn search_limits() {
+        assert_eq!(
+            parse("").unwrap().limits,
+            SearchLimits::depth(DEFAULT_DEPTH)
+        );
+        assert_eq!(
+            parse("bench").unwrap().limits,
+            SearchLimits::depth(DEFAULT_BENCH_DEPTH)
+        );
+        assert_eq!(parse("--depth 4").unwrap().limits, SearchLimits::depth(4));
+        assert_eq!(
+            parse("--movetime 250").unwrap().limits,
+            SearchLimits::movetime(Duration::from_millis(250))
+        );
+        let limits = parse("--clock 60000 --inc 500 --nodes 1000")
+            .unwrap()
+            .limits;
+        assert_eq!(limits.clock, Some(Duration::from_secs(60)));
+        assert_eq!(limits.increment, Duration::from_millis(500));
+        assert_eq!(limits.nodes, Some(1000));
+        assert_eq!(limits.depth, None);
+        assert_eq!(
+            parse("--depth deep").err().unwrap(),
+            "--depth needs a number, not deep"
+        );
+        assert_eq!(
+            parse("--movetime -5").err().unwrap(),
+            "--movetime needs a number, not -5"
+        );
+    }
+
+    #[test]
+    fn engine_options() {
+        let options =
+            parse("play --rules tablut --engine defenders --no-hints --multipv 3").unwrap();
+        assert_eq!(options.rules, Some(Rules::tablut()));
+        assert_eq!(options.engine_side, EngineSide::Defenders);
+        assert!(!options.hints);
+        assert_eq!(options.multi_pv, 3);
+        assert_eq!(options.hash_mb, DEFAULT_HASH_MB);
+        assert_eq!(parse("--hash 64").unwrap().hash_mb, 64);
+        assert_eq!(
+            parse("--hash 18446744073709551615").err().unwrap(),
+            format!("--hash can be at most {} MB", MAX_HASH_MB)
+        );
+        assert_eq!(
+            parse("--rules chess").err().unwrap(),
+            "Unknown rule set: chess"
+        );
+        assert_eq!(
+            parse("--engine white").err().unwrap(),
+            "Unknown side: white"
+        );
+    }
+
+    #[test]
+    fn unknown_options_and_missing_values() {
+        assert_eq!(parse("--fast").err().unwrap(), "Unknown option: --fast");
+        for option in ["--rules", "--depth", "--movetime", "--hash", "--save"] {
+            assert_eq!(
+                parse(option).err().unwrap(),
+                format!("{} needs a value", option)
+            );
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Instant;
+
+use tafl_rust::engine::{EngineBenchmark, SearchInfo};
+use tafl_rust::game::{search, EngineSide, Game};
+use tafl_rust::record::GameRecord;
+use tafl_rust::ui::{ConsoleUI, UI};
+use tafl_rust::{board, perft, Board, Move, Outcome, Rules, TaflAI, TranspositionTable};
+
+use cli::{Command, Options};
+
+mod cli;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::parse_args(&args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut tafl_ai = TaflAI {
+        limits: options.limits,
+        multi_pv: options.multi_pv,
+        ttable: TranspositionTable::with_size_mb(options.hash_mb),
+    };
+    let (mut b, game_record) = match starting_position(&options, &tafl_ai.ttable) {
+        Ok(start) => start,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match options.command {
+        Command::Play => play(&options, &mut tafl_ai, game_record),
+        Command::Analyze => analyze(tafl_ai, &b),
+        Command::Perft { depth, divide } => run_perft(&mut b, depth, divide, &tafl_ai.ttable),
+        Command::Bench => bench(&mut tafl_ai),
+        Command::Selfplay { games } => selfplay(&options, &mut tafl_ai, &game_record, games),
+    }
+}
+
+// The position to start from, along with the record of any game that led to it.
+fn starting_position(
+    options: &Options,
+    tt: &TranspositionTable,
+) -> Result<(Board, GameRecord), String> {
+    if let Some(path) = &options.position_file {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        // a game record starts with its tags, a position with its rule set
+        if text.trim_start().starts_with('[') {
+            let record = GameRecord::read(&text, tt)
+                .map_err(|e| format!("Unable to load {}: {}", path, e))?;
+            return Ok((record.replay(tt), record));
+        }
+        return position_from_notation(&text, tt);
+    }
+    if let Some(notation) = &options.position {
+        return position_from_notation(notation, tt);
+    }
+
+    let mut b = match options.rules {
+        Some(rules) => Board::new(rules),
+        None => board::STARTING_BOARD.clone(),
+    };
+    b.rehash(tt);
+    let record = GameRecord::new(&b);
+    Ok((b, record))
+}
+
+fn position_from_notation(
+    notation: &str,
+    tt: &TranspositionTable,
+) -> Result<(Board, GameRecord), String> {
+    let mut b = Board::from_notation(notation).map_err(|e| format!("Invalid position: {}", e))?;
+    b.rehash(tt);
+    let record = GameRecord::new(&b);
+    Ok((b, record))
+}
+
+fn play(options: &Options, tafl_ai: &mut TaflAI, game_record: GameRecord) {
+    let mut console_ui = ConsoleUI::new();
+    let mut game = Game::new(game_record, tafl_ai, options.engine_side);
+    game.hints = options.hints;
+    game.play_out(tafl_ai, &mut console_ui, |game| save(options, &game.record));
+}
+
+fn analyze(tafl_ai: TaflAI, b: &Board) {
+    let console_ui = ConsoleUI::new();
+    console_ui.render_board(b);
+    if let Some(outcome) = b.outcome() {
+        console_ui.game_over(outcome);
+        return;
+    }
+
+    println!("Press Enter to stop the search");
+    let now = Instant::now();
+    let handle = tafl_ai.spawn_search(b.clone());
+    let stop = handle.stop_flag();
+    // left blocked on input if the search ends first, which is fine as the program exits then
+    thread::spawn(move || {
+        let mut line = String::new();
+        if let Ok(1..) = std::io::stdin().read_line(&mut line) {
+            stop.store(true, Ordering::Relaxed);
+        }
+    });
+    for info in handle.info.iter() {
+        console_ui.render_info(b, &info);
+    }
+    let (_, recommendation) = handle.join();
+    let benchmark = EngineBenchmark {
+        recommendation,
+        elapsed: now.elapsed(),
+    };
+    console_ui.render_eval(b, &benchmark);
+}
+
+fn run_perft(b: &mut Board, depth: usize, divide: bool, tt: &TranspositionTable) {
+    let now = Instant::now();
+    let nodes = if divide {
+        let mut total = 0;
+        for (m, nodes) in perft::divide(b, depth, tt) {
+            println!("{}: {}", m, nodes);
+            total += nodes;
+        }
+        total
+    } else {
+        perft::perft(b, depth, tt)
+    };
+    let elapsed = now.elapsed();
+    println!(
+        "Nodes: {} ({:.2?}, {:.0} nodes/s)",
+        nodes,
+        elapsed,
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn bench(tafl_ai: &mut TaflAI) {
+    let mut total_nodes = 0;
+    let now = Instant::now();
+    for name in ["ard-ri", "brandubh", "tablut", "fetlar", "copenhagen"] {
+        let mut b = Board::new(Rules::from_name(name).unwrap());
+        b.rehash(&tafl_ai.ttable);
+        tafl_ai.ttable.clear();
+        let benchmark = search(tafl_ai, &b, |_| {});
+        let rec = &benchmark.recommendation;
+        println!(
+            "{:<12} {:>6} {:>12} nodes {:>10.2?}",
+            name,
+            b.short_move_notation(rec.best_move),
+            rec.nnodes,
+            benchmark.elapsed
+        );
+        total_nodes += rec.nnodes;
+    }
+    let elapsed = now.elapsed();
+    println!(
+        "Total: {} nodes ({:.2?}, {:.0} nodes/s)",
+        total_nodes,
+        elapsed,
+        total_nodes as f64 / elapsed.as_secs_f64()
+    );
+}
+
+// Prints only the moves, numbered by ply.
+struct MoveListUI;
+
+impl UI for MoveListUI {
+    fn get_move(&mut self, _: &Board) -> Option<Move> {
+        None
+    }
+    fn render_board(&self, _: &Board) {}
+    fn render_eval(&self, _: &Board, _: &EngineBenchmark) {}
+    fn render_info(&self, _: &Board, _: &SearchInfo) {}
+    fn render_move(&self, b: &Board, m: Move, _: bool) {
+        println!("{}. {}", b.history.len() + 1, b.long_move_notation(m));
+    }
+    fn game_over(&self, _: Outcome) {}
+}
+
+fn selfplay(options: &Options, tafl_ai: &mut TaflAI, start: &GameRecord, games: usize) {
+    let (mut attacker_wins, mut defender_wins, mut draws) = (0, 0, 0);
+    for game_number in 1..=games {
+        let mut game = Game::new(start.clone(), tafl_ai, EngineSide::Both);
+        game.hints = false;
+        let outcome = game.play_out(tafl_ai, &mut MoveListUI, |game| save(options, &game.record));
+        // the engine always has a move, so every game is played to the end
+        let outcome = outcome.expect("self-play game ended without an outcome");
+        println!("Game {}: {}", game_number, outcome);
+        match outcome {
+            Outcome::AttackersWin(_) => attacker_wins += 1,
+            Outcome::DefendersWin(_) => defender_wins += 1,
+            Outcome::Draw(_) => draws += 1,
+        }
+    }
+    println!(
+        "Attackers {} - Defenders {} - Draws {}",
+        attacker_wins, defender_wins, draws
+    );
+}
+
+fn save(options: &Options, game_record: &GameRecord) {
+    if let Some(path) = &options.save {
+        if let Err(e) = std::fs::write(path, game_record.to_string()) {
+            eprintln!("Unable to save {}: {}", path, e);
+        }
+    }
+}
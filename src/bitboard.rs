@@ -0,0 +1,346 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+/// The largest supported board is 13x13. Every board size shares the same square indexing,
+/// `row * MAX_BOARD_SIZE + col`, so smaller boards simply leave the outer rows and columns empty.
+pub const MAX_BOARD_SIZE: usize = 13;
+pub const NUM_SQUARES: usize = MAX_BOARD_SIZE * MAX_BOARD_SIZE;
+
+const WORDS: usize = NUM_SQUARES.div_ceil(64);
+const WORD_BITS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Bitboard([u64; WORDS]);
+
+pub const EMPTY_BOARD: Bitboard = Bitboard([0; WORDS]);
+
+const FIRST_COLUMN: Bitboard = column(0);
+const LAST_COLUMN: Bitboard = column(MAX_BOARD_SIZE - 1);
+
+const fn column(col: usize) -> Bitboard {
+    let mut bb = EMPTY_BOARD;
+    let mut row = 0;
+    while row < MAX_BOARD_SIZE {
+        bb = bb.with(row * MAX_BOARD_SIZE + col);
+        row += 1;
+    }
+    bb
+}
+
+impl Bitboard {
+    #[inline(always)]
+    pub const fn square(index: usize) -> Self {
+        let mut words = [0; WORDS];
+        words[index / WORD_BITS] = 1 << (index % WORD_BITS);
+        Bitboard(words)
+    }
+
+    pub const fn from_squares(indices: &[usize]) -> Self {
+        let mut bb = EMPTY_BOARD;
+        let mut i = 0;
+        while i < indices.len() {
+            bb = bb.with(indices[i]);
+            i += 1;
+        }
+        bb
+    }
+
+    #[inline(always)]
+    pub const fn with(self, index: usize) -> Self {
+        let mut words = self.0;
+        words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+        Bitboard(words)
+    }
+
+    #[inline(always)]
+    pub const fn contains(self, index: usize) -> bool {
+        self.0[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    #[inline(always)]
+    pub const fn is_empty(self) -> bool {
+        let mut i = 0;
+        while i < WORDS {
+            if self.0[i] != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    #[inline(always)]
+    pub fn count_ones(self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Index of the lowest set square, or `WORDS * 64` if the board is empty.
+    #[inline(always)]
+    pub fn trailing_zeros(self) -> u32 {
+        let mut zeros = 0;
+        for w in self.0 {
+            if w != 0 {
+                return zeros + w.trailing_zeros();
+            }
+            zeros += WORD_BITS as u32;
+        }
+        zeros
+    }
+
+    /// Index of the highest set square. The board must not be empty.
+    #[inline(always)]
+    pub fn last_square(self) -> usize {
+        for i in (0..WORDS).rev() {
+            if self.0[i] != 0 {
+                return i * WORD_BITS + (WORD_BITS - 1) - self.0[i].leading_zeros() as usize;
+            }
+        }
+        panic!("last_square called on an empty bitboard");
+    }
+
+    /// Squares orthogonally adjacent to any square of the board. This can include squares
+    /// beyond the edge of a smaller board, so callers should mask the result.
+    #[inline(always)]
+    pub fn neighbours(self) -> Self {
+        ((self << 1) & !FIRST_COLUMN)
+            | ((self >> 1) & !LAST_COLUMN)
+            | (self << MAX_BOARD_SIZE)
+            | (self >> MAX_BOARD_SIZE)
+    }
+
+    /// Every square of `passable` connected to this board by orthogonal steps that stay
+    /// within `passable`.
+    pub fn flood_fill(self, passable: Self) -> Self {
+        let mut filled = self & passable;
+        loop {
+            let grown = (filled | filled.neighbours()) & passable;
+            if grown == filled {
+                return filled;
+            }
+            filled = grown;
+        }
+    }
+}
+
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<usize> {
+        for (i, w) in self.0 .0.iter_mut().enumerate() {
+            if *w != 0 {
+                let index = i * WORD_BITS + w.trailing_zeros() as usize;
+                *w &= *w - 1;
+                return Some(index);
+            }
+        }
+        None
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = usize;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        Squares(self)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(mut self, rhs: Self) -> Self {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    #[inline(always)]
+    fn bitand_assign(&mut self, rhs: Self) {
+        for i in 0..WORDS {
+            self.0[i] &= rhs.0[i];
+        }
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(mut self, rhs: Self) -> Self {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    #[inline(always)]
+    fn bitor_assign(&mut self, rhs: Self) {
+        for i in 0..WORDS {
+            self.0[i] |= rhs.0[i];
+        }
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(mut self, rhs: Self) -> Self {
+        self ^= rhs;
+        self
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    #[inline(always)]
+    fn bitxor_assign(&mut self, rhs: Self) {
+        for i in 0..WORDS {
+            self.0[i] ^= rhs.0[i];
+        }
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn not(self) -> Self {
+        let mut words = self.0;
+        for w in words.iter_mut() {
+            *w = !*w;
+        }
+        Bitboard(words)
+    }
+}
+
+// Shifts move every square towards higher (Shl) or lower (Shr) indices. Bits shifted past
+// the last square are kept in the unused high bits of the top word, so callers should mask
+// the result with the squares of their board. Shifting by the whole width or more empties it.
+impl Shl<usize> for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn shl(self, n: usize) -> Self {
+        let word_shift = n / WORD_BITS;
+        if word_shift >= WORDS {
+            return EMPTY_BOARD;
+        }
+        let bit_shift = n % WORD_BITS;
+        let mut words = [0; WORDS];
+        for i in (word_shift..WORDS).rev() {
+            words[i] = self.0[i - word_shift] << bit_shift;
+            if bit_shift > 0 && i > word_shift {
+                words[i] |= self.0[i - word_shift - 1] >> (WORD_BITS - bit_shift);
+            }
+        }
+        Bitboard(words)
+    }
+}
+
+impl Shr<usize> for Bitboard {
+    type Output = Self;
+
+    #[inline(always)]
+    fn shr(self, n: usize) -> Self {
+        let word_shift = n / WORD_BITS;
+        if word_shift >= WORDS {
+            return EMPTY_BOARD;
+        }
+        let bit_shift = n % WORD_BITS;
+        let mut words = [0; WORDS];
+        for (i, word) in words.iter_mut().enumerate().take(WORDS - word_shift) {
+            *word = self.0[i + word_shift] >> bit_shift;
+            if bit_shift > 0 && i + word_shift + 1 < WORDS {
+                *word |= self.0[i + word_shift + 1] << (WORD_BITS - bit_shift);
+            }
+        }
+        Bitboard(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(row: usize, col: usize) -> usize {
+        row * MAX_BOARD_SIZE + col
+    }
+
+    #[test]
+    fn neighbours_do_not_wrap_rows() {
+        // (4, 12) and (5, 0) sit either side of the first word boundary
+        let n = Bitboard::square(sq(4, 12)).neighbours();
+        let expected = Bitboard::from_squares(&[sq(4, 11), sq(3, 12), sq(5, 12)]);
+        assert_eq!(n, expected);
+
+        let n = Bitboard::square(sq(5, 0)).neighbours();
+        let expected = Bitboard::from_squares(&[sq(5, 1), sq(4, 0), sq(6, 0)]);
+        assert_eq!(n, expected);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_walls() {
+        let mut board = EMPTY_BOARD;
+        for row in 0..MAX_BOARD_SIZE {
+            for col in 0..MAX_BOARD_SIZE {
+                board = board.with(sq(row, col));
+            }
+        }
+        let mut wall = EMPTY_BOARD;
+        for row in 0..MAX_BOARD_SIZE {
+            wall = wall.with(sq(row, 6));
+        }
+
+        let left = Bitboard::square(sq(12, 0)).flood_fill(board & !wall);
+        assert_eq!(left.count_ones() as usize, MAX_BOARD_SIZE * 6);
+        assert!(!left.contains(sq(0, 7)));
+    }
+
+    #[test]
+    fn shifts_carry_across_words() {
+        // (4, 11) is the last square of the first word, (9, 10) the last of the second
+        let bb = Bitboard::from_squares(&[sq(4, 11), sq(9, 10)]);
+        assert_eq!(bb << 1, Bitboard::from_squares(&[sq(4, 12), sq(9, 11)]));
+        assert_eq!(bb << 13, Bitboard::from_squares(&[sq(5, 11), sq(10, 10)]));
+        assert_eq!(bb << 64, Bitboard::from_squares(&[sq(9, 10), sq(14, 9)]));
+        assert_eq!((bb << 1) >> 1, bb);
+        assert_eq!((bb << 13) >> 13, bb);
+        assert_eq!((bb << 60) >> 60, bb);
+        assert_eq!((bb << 70) >> 70, Bitboard::square(sq(4, 11)));
+        assert_eq!(bb >> 64, Bitboard::square(sq(4, 11)));
+        assert_eq!(bb >> 129, EMPTY_BOARD);
+        assert_eq!(Bitboard::square(sq(12, 12)) >> 128, Bitboard::square(40));
+    }
+
+    #[test]
+    fn shifts_past_the_width_empty_the_board() {
+        let bb = Bitboard::from_squares(&[0, sq(6, 6), sq(12, 12)]);
+        for n in [WORDS * WORD_BITS, WORDS * WORD_BITS + 1, 1000] {
+            assert_eq!(bb << n, EMPTY_BOARD);
+            assert_eq!(bb >> n, EMPTY_BOARD);
+        }
+        assert_eq!(bb >> (WORDS * WORD_BITS - 1), EMPTY_BOARD);
+        assert_eq!(
+            Bitboard::square(0) << (WORDS * WORD_BITS - 1),
+            Bitboard([0, 0, 1 << 63])
+        );
+    }
+
+    #[test]
+    fn scans_find_squares_in_every_word() {
+        for index in [0, 63, 64, 127, 128, NUM_SQUARES - 1] {
+            let bb = Bitboard::square(index);
+            assert_eq!(bb.trailing_zeros() as usize, index);
+            assert_eq!(bb.last_square(), index);
+        }
+        let bb = Bitboard::from_squares(&[70, 130]);
+        assert_eq!(bb.trailing_zeros(), 70);
+        assert_eq!(bb.last_square(), 130);
+        assert_eq!(EMPTY_BOARD.trailing_zeros() as usize, WORDS * WORD_BITS);
+    }
+}
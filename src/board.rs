@@ -1,8 +1,13 @@
-pub const BOARD_SIZE: usize = 7;
 pub const DIRS: [(isize, isize); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
-pub type Bitboard = u64;
-pub const EMPTY_BOARD: Bitboard = 0;
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use crate::bitboard::{Bitboard, EMPTY_BOARD, MAX_BOARD_SIZE};
+use crate::movegen::has_legal_moves;
+use crate::notation::square_name;
+use crate::rules::{Escape, HostileThrone, KingCapture, Repetition, Rules};
 use crate::ttable::{
     TranspositionTable, PIECE_TYPE_ATTACKER_IDX, PIECE_TYPE_DEFENDER_IDX, PIECE_TYPE_KING_IDX,
 };
@@ -14,6 +19,49 @@ pub enum PieceType {
     King,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reason {
+    KingCaptured,
+    Encirclement,
+    KingEscaped,
+    EdgeFort,
+    Repetition,
+    MoveLimit,
+    NoLegalMoves,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    AttackersWin(Reason),
+    DefendersWin(Reason),
+    Draw(Reason),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Reason::KingCaptured => "the king was captured",
+            Reason::Encirclement => "the defenders are encircled",
+            Reason::KingEscaped => "the king escaped",
+            Reason::EdgeFort => "the king is safe in an edge fort",
+            Reason::Repetition => "the position was repeated",
+            Reason::MoveLimit => "the move limit was reached without a capture",
+            Reason::NoLegalMoves => "no legal moves are left",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::AttackersWin(reason) => write!(f, "Attackers win: {}", reason),
+            Outcome::DefendersWin(reason) => write!(f, "Defenders win: {}", reason),
+            Outcome::Draw(reason) => write!(f, "Draw: {}", reason),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Move {
     pub start_index: usize,
@@ -23,10 +71,25 @@ pub struct Move {
 
 #[derive(Clone)]
 pub struct MoveHistoryElement {
+    pub m: Move,
+    pub captured: Bitboard,
+    /// The result before the move, restored when it is unmade.
+    pub result: Option<Outcome>,
+    // the position before the move
     pub attacker_board: Bitboard,
     pub defender_board: Bitboard,
     pub king_board: Bitboard,
     current_hash: usize,
+    quiet_moves: usize,
+}
+
+/// What the last move did.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MoveInfo {
+    pub m: Move,
+    pub captured: Bitboard,
+    /// The result after the move.
+    pub result: Option<Outcome>,
 }
 
 pub const NULL_MOVE: Move = Move {
@@ -35,20 +98,14 @@ pub const NULL_MOVE: Move = Move {
     piece_type: PieceType::Attacker, // arbitrary
 };
 
-impl Move {
-    pub fn to_string(&self) -> String {
-        let (start_row, start_col) = index_to_rc(self.start_index);
-        let (end_row, end_col) = index_to_rc(self.end_index);
-        let mut s = String::with_capacity(5); // "k" + "a1" + "a1" = 5 chars max
-        if self.piece_type == PieceType::King {
-            s.push('k');
-        } else {
-            s.push((start_col as u8 + b'a') as char);
-            s.push_str(&(start_row + 1).to_string());
-        }
-        s.push((end_col as u8 + b'a') as char);
-        s.push_str(&(end_row + 1).to_string());
-        s
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            square_name(self.start_index),
+            square_name(self.end_index)
+        )
     }
 }
 
@@ -58,188 +115,551 @@ impl PartialEq for Move {
             && self.end_index == other.end_index
             && self.piece_type == other.piece_type
     }
+}
+
+/// Square masks that depend only on the size of the board.
+pub struct Geometry {
+    pub size: usize,
+    /// Every square on the board.
+    pub squares: Bitboard,
+    pub edges: Bitboard,
+    pub corners: Bitboard,
+    /// The central square.
+    pub throne: Bitboard,
+}
+
+static GEOMETRIES: [Geometry; MAX_BOARD_SIZE + 1] = make_geometries();
+
+const fn make_geometries() -> [Geometry; MAX_BOARD_SIZE + 1] {
+    let mut geometries = [const {
+        Geometry {
+            size: 0,
+            squares: EMPTY_BOARD,
+            edges: EMPTY_BOARD,
+            corners: EMPTY_BOARD,
+            throne: EMPTY_BOARD,
+        }
+    }; MAX_BOARD_SIZE + 1];
+    let mut size = 1;
+    while size <= MAX_BOARD_SIZE {
+        let last = size - 1;
+        let mut squares = EMPTY_BOARD;
+        let mut edges = EMPTY_BOARD;
+        let mut index = 0;
+        while index < size * size {
+            let (row, col) = (index / size, index % size);
+            squares = squares.with(rc_to_index(row, col));
+            if row == 0 || row == last || col == 0 || col == last {
+                edges = edges.with(rc_to_index(row, col));
+            }
+            index += 1;
+        }
+        geometries[size] = Geometry {
+            size,
+            squares,
+            edges,
+            corners: Bitboard::from_squares(&[
+                rc_to_index(0, 0),
+                rc_to_index(0, last),
+                rc_to_index(last, 0),
+                rc_to_index(last, last),
+            ]),
+            throne: Bitboard::square(rc_to_index(size / 2, size / 2)),
+        };
+        size += 1;
+    }
+    geometries
+}
 
-    fn ne(&self, other: &Self) -> bool {
-        self.start_index != other.start_index
-            || self.end_index != other.end_index
-            || self.piece_type != other.piece_type
+impl Geometry {
+    /// The geometry of a `size` x `size` board, for any size up to `MAX_BOARD_SIZE`.
+    pub const fn of(size: usize) -> &'static Geometry {
+        &GEOMETRIES[size]
     }
 }
 
 #[derive(Clone)]
 pub struct Board {
+    pub rules: Rules,
+    pub geometry: &'static Geometry,
     pub attacker_board: Bitboard,
     pub defender_board: Bitboard,
     pub king_board: Bitboard,
     pub offlimits_board: Bitboard,
     pub attacker_move: bool,
-    pub attacker_win: bool,
-    pub defender_win: bool,
-    pub stalemate: bool,
+    /// Set by the move that ended the game.
+    pub result: Option<Outcome>,
     pub current_hash: usize,
+    /// Moves played since the last capture, by either side.
+    pub quiet_moves: usize,
     pub history: Vec<MoveHistoryElement>,
+    // how often each hash in `history` occurs, for the repetition rules
+    position_counts: HashMap<usize, usize>,
 }
 
-pub const STARTING_BOARD: Board = Board {
-    attacker_board: 123437837206556,
-    defender_board: 7558594560,
-    king_board: 16777216,
-    offlimits_board: 285873039999041,
-    attacker_move: false,
-    attacker_win: false,
-    defender_win: false,
-    stalemate: false,
-    current_hash: 0,
-    history: Vec::new(),
-};
+pub static STARTING_BOARD: LazyLock<Board> = LazyLock::new(|| Board::new(Rules::ard_ri()));
 
 #[inline]
 pub const fn rc_to_index(row: usize, col: usize) -> usize {
-    row * BOARD_SIZE + col
+    row * MAX_BOARD_SIZE + col
 }
 
 #[inline]
 pub const fn index_to_rc(index: usize) -> (usize, usize) {
-    (index / BOARD_SIZE, index % BOARD_SIZE)
+    (index / MAX_BOARD_SIZE, index % MAX_BOARD_SIZE)
 }
 
 #[inline]
-pub fn inbounds(row: isize, col: isize) -> bool {
-    return row >= 0 && row < BOARD_SIZE as isize && col >= 0 && col < BOARD_SIZE as isize;
+pub fn inbounds(row: isize, col: isize, size: usize) -> bool {
+    row >= 0 && row < size as isize && col >= 0 && col < size as isize
 }
 
 impl Board {
-    pub fn king_captured(&self) -> bool {
+    /// The starting position of a rule set. The hash is left at zero until `rehash` is called
+    /// with the transposition table the board will be searched with.
+    pub fn new(rules: Rules) -> Self {
+        Board::with_position(rules, rules.start_position)
+            .unwrap_or_else(|e| panic!("bad start position for {}: {}", rules.name, e))
+    }
+
+    /// A board with no pieces on it and the attackers to move.
+    pub fn empty(rules: Rules) -> Self {
+        let geometry = Geometry::of(rules.board_size);
+        let mut offlimits_board = geometry.throne;
+        if rules.restricted_corners {
+            offlimits_board |= geometry.corners;
+        }
+        Board {
+            rules,
+            geometry,
+            attacker_board: EMPTY_BOARD,
+            defender_board: EMPTY_BOARD,
+            king_board: EMPTY_BOARD,
+            offlimits_board,
+            attacker_move: true,
+            result: None,
+            current_hash: 0,
+            quiet_moves: 0,
+            history: Vec::new(),
+            position_counts: HashMap::new(),
+        }
+    }
+
+    /// Recomputes the hash from scratch with the keys of `tt`, which every table lookup and
+    /// move made with it then relies on.
+    pub fn rehash(&mut self, tt: &TranspositionTable) {
+        self.current_hash = tt.hash_from_board(self);
+    }
+
+    /// Squares that act as the far side of a capture made by the side that just moved
+    /// `piece_type`: its own pieces plus any hostile restricted squares.
+    pub fn hammer_board(&self, piece_type: PieceType) -> Bitboard {
+        let throne_empty = (self.geometry.throne & self.king_board).is_empty();
+        let mut hostile = EMPTY_BOARD;
+        if self.rules.hostile_corners {
+            hostile |= self.offlimits_board & self.geometry.corners;
+        }
+        let hostile_throne = match self.rules.hostile_throne {
+            HostileThrone::Never => false,
+            HostileThrone::WhenEmpty => throne_empty,
+            HostileThrone::Always => throne_empty || piece_type != PieceType::Attacker,
+        };
+        if hostile_throne {
+            hostile |= self.geometry.throne;
+        }
+
+        match piece_type {
+            PieceType::Attacker => self.attacker_board | hostile,
+            _ if self.rules.armed_king => self.defender_board | self.king_board | hostile,
+            _ => self.defender_board | hostile,
+        }
+    }
+
+    /// Whether an attacker landing on `end_index` captures the king, asked either before or
+    /// after it moves.
+    pub fn king_captured(&self, end_index: usize) -> bool {
         let (king_row, king_col) = self.king_coordinates();
+        let (end_row, end_col) = index_to_rc(end_index);
+        if king_row.abs_diff(end_row) + king_col.abs_diff(end_col) != 1 {
+            return false;
+        }
 
+        let (throne_row, throne_col) = index_to_rc(self.geometry.throne.trailing_zeros() as usize);
+        let throne_dist = king_row.abs_diff(throne_row) + king_col.abs_diff(throne_col);
+        let surround = match self.rules.king_capture {
+            KingCapture::TwoSided => false,
+            KingCapture::FourSided => true,
+            KingCapture::ThroneDependent => throne_dist <= 1,
+        };
+        if !surround {
+            return valid_capture(
+                self.size(),
+                self.hammer_board(PieceType::Attacker),
+                self.king_board,
+                (end_row as isize, end_col as isize),
+                (king_row as isize, king_col as isize),
+            );
+        }
+
+        // every side must be an attacker or a restricted square, or off the board if the
+        // rules let the edge stand in for an attacker
+        let hostile = self.attacker_board | self.offlimits_board | Bitboard::square(end_index);
         for dir in DIRS.iter() {
             let new_row = king_row as isize + dir.0;
             let new_col = king_col as isize + dir.1;
 
-            if !inbounds(new_row, new_col) {
-                continue;
+            if !inbounds(new_row, new_col, self.size()) {
+                if self.rules.king_captured_on_edge {
+                    continue;
+                }
+                return false;
             }
 
-            let new_index = rc_to_index(new_row as usize, new_col as usize);
-            if self.offlimits_board & (1 << new_index) != 0 {
-                continue;
+            if !hostile.contains(rc_to_index(new_row as usize, new_col as usize)) {
+                return false;
             }
+        }
+        true
+    }
 
-            if self.attacker_board & (1 << new_index) == 0 {
-                return false;
+    /// Pieces captured in a shieldwall by a `piece_type` that just landed on the edge square
+    /// `end_index`: a row of two or more enemy pieces along the edge, bracketed at both ends
+    /// and each faced by one of the mover's pieces. The king can be part of the row but is
+    /// never captured this way.
+    pub fn shieldwall_captures(&self, end_index: usize, piece_type: PieceType) -> Bitboard {
+        let (friends, enemies, capturable) = match piece_type {
+            PieceType::Attacker => (
+                self.attacker_board,
+                self.defender_board | self.king_board,
+                self.defender_board,
+            ),
+            PieceType::King if !self.rules.armed_king => return EMPTY_BOARD,
+            _ if self.rules.armed_king => (
+                self.defender_board | self.king_board,
+                self.attacker_board,
+                self.attacker_board,
+            ),
+            _ => (
+                self.defender_board,
+                self.attacker_board,
+                self.attacker_board,
+            ),
+        };
+        let brackets = self.hammer_board(piece_type);
+
+        let (row, col) = index_to_rc(end_index);
+        let last = self.size() - 1;
+        let mut inward_dirs = Vec::with_capacity(2);
+        if row == 0 {
+            inward_dirs.push((1, 0));
+        } else if row == last {
+            inward_dirs.push((-1, 0));
+        }
+        if col == 0 {
+            inward_dirs.push((0, 1));
+        } else if col == last {
+            inward_dirs.push((0, -1));
+        }
+
+        let mut captured = EMPTY_BOARD;
+        for (inward_row, inward_col) in inward_dirs {
+            for sign in [-1, 1] {
+                // walk along the edge, perpendicular to the inward direction
+                let (dr, dc) = (inward_col * sign, inward_row * sign);
+                let mut wall = EMPTY_BOARD;
+                let mut r = row as isize + dr;
+                let mut c = col as isize + dc;
+                while inbounds(r, c, self.size()) {
+                    let index = rc_to_index(r as usize, c as usize);
+                    if !enemies.contains(index) {
+                        if brackets.contains(index) && wall.count_ones() >= 2 {
+                            captured |= wall & capturable;
+                        }
+                        break;
+                    }
+                    let front = rc_to_index((r + inward_row) as usize, (c + inward_col) as usize);
+                    if !friends.contains(front) {
+                        break;
+                    }
+                    wall |= Bitboard::square(index);
+                    r += dr;
+                    c += dc;
+                }
             }
         }
+        captured
+    }
 
-        true
+    /// Whether the king sits on the edge inside an exit fort: a group of defenders that
+    /// encloses the king and some empty squares for it to move to, keeps every attacker out
+    /// and cannot be broken because none of its pieces can ever be captured.
+    pub fn is_edge_fort(&self) -> bool {
+        if (self.king_board & self.geometry.edges).is_empty() {
+            return false;
+        }
+
+        let region = self
+            .king_board
+            .flood_fill(self.geometry.squares & !self.defender_board);
+        if region.count_ones() < 2 || !(region & self.attacker_board).is_empty() {
+            return false;
+        }
+
+        // Find the defenders that can never be captured: a defender is safe on an axis if
+        // either neighbour is off the board, inside the fort or another safe defender, as
+        // long as that square is not hostile. Start from every defender and drop the unsafe
+        // ones until nothing changes.
+        let hostile = self.hammer_board(PieceType::Attacker) & self.offlimits_board;
+        let size = self.size();
+        let mut solid = self.defender_board;
+        loop {
+            let safe = (region | solid) & !hostile;
+            let protects = |row: isize, col: isize| {
+                !inbounds(row, col, size) || safe.contains(rc_to_index(row as usize, col as usize))
+            };
+            let mut still_solid = EMPTY_BOARD;
+            for index in solid {
+                let (row, col) = index_to_rc(index);
+                let (row, col) = (row as isize, col as isize);
+                if (protects(row - 1, col) || protects(row + 1, col))
+                    && (protects(row, col - 1) || protects(row, col + 1))
+                {
+                    still_solid |= Bitboard::square(index);
+                }
+            }
+            if still_solid == solid {
+                break;
+            }
+            solid = still_solid;
+        }
+
+        let wall = region.neighbours() & self.defender_board;
+        (wall & !solid).is_empty()
+    }
+
+    /// Whether the attackers have surrounded the king and every defender so that none of them
+    /// can reach the edge of the board.
+    pub fn is_encircled(&self) -> bool {
+        let reachable = (self.defender_board | self.king_board)
+            .flood_fill(self.geometry.squares & !self.attacker_board);
+        (reachable & self.geometry.edges).is_empty()
+    }
+
+    /// Squares the king wins by reaching.
+    #[inline]
+    pub fn escape_board(&self) -> Bitboard {
+        match self.rules.escape {
+            Escape::Edge => self.geometry.edges,
+            Escape::Corner => self.geometry.corners,
+        }
+    }
+
+    /// How the game has ended, if it has: either by the last move or because the side to
+    /// move has no legal moves.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.result.is_some() {
+            return self.result;
+        }
+        if has_legal_moves(self) {
+            return None;
+        }
+        Some(self.no_moves_outcome())
+    }
+
+    /// The outcome when the side to move has no legal moves.
+    pub fn no_moves_outcome(&self) -> Outcome {
+        match (self.rules.no_moves_loses, self.attacker_move) {
+            (false, _) => Outcome::Draw(Reason::NoLegalMoves),
+            (true, true) => Outcome::DefendersWin(Reason::NoLegalMoves),
+            (true, false) => Outcome::AttackersWin(Reason::NoLegalMoves),
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.geometry.size
     }
 
     #[inline]
     pub fn king_coordinates(&self) -> (usize, usize) {
-        return index_to_rc(self.king_board.trailing_zeros() as usize);
+        index_to_rc(self.king_board.trailing_zeros() as usize)
     }
 
     #[inline]
     pub fn king_index(&self) -> usize {
-        return self.king_board.trailing_zeros() as usize;
+        self.king_board.trailing_zeros() as usize
     }
 
-    pub fn is_repetition(&self) -> bool {
-        if self.history.len() < 2 {
-            return false;
-        }
-        let mut i = if self.attacker_move { 1 } else { 0 };
-        while i < self.history.len() {
-            let p = &self.history[i];
-            if self.defender_board == p.defender_board
-                && self.attacker_board == p.attacker_board
-                && self.king_board == p.king_board
-            {
-                return true;
-            }
-            i += 2;
-        }
-        false
+    /// How many times the current position has occurred with the same side to move, including
+    /// now. The side to move is part of the hash, so equal hashes mean equal positions.
+    pub fn repetitions(&self) -> usize {
+        1 + self
+            .position_counts
+            .get(&self.current_hash)
+            .copied()
+            .unwrap_or(0)
     }
 
-    pub fn make_move(&mut self, m: Move, tt: &TranspositionTable) {
-        let hist_move = MoveHistoryElement {
-            attacker_board: self.attacker_board,
-            defender_board: self.defender_board,
-            king_board: self.king_board,
-            current_hash: self.current_hash,
-        };
+    /// Forgets the moves that led to the current position, making it the start of the game.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.position_counts.clear();
+    }
 
-        let piece_mask = 1 << m.start_index | 1 << m.end_index;
-        let capturer_board: u64;
-        let capturee_board: u64;
+    fn move_piece(&mut self, m: Move) {
+        let piece_mask = Bitboard::square(m.start_index) | Bitboard::square(m.end_index);
         match m.piece_type {
-            PieceType::Attacker => {
-                self.attacker_board ^= piece_mask;
-                self.current_hash ^= tt.init_hash[m.end_index][PIECE_TYPE_ATTACKER_IDX];
-                self.current_hash ^= tt.init_hash[m.start_index][PIECE_TYPE_ATTACKER_IDX];
-                capturer_board = self.attacker_board;
-                capturee_board = self.defender_board;
-            }
-            PieceType::Defender => {
-                self.defender_board ^= piece_mask;
-                self.current_hash ^= tt.init_hash[m.end_index][PIECE_TYPE_DEFENDER_IDX];
-                self.current_hash ^= tt.init_hash[m.start_index][PIECE_TYPE_DEFENDER_IDX];
-                capturer_board = self.defender_board | self.king_board;
-                capturee_board = self.attacker_board;
-            }
-            PieceType::King => {
-                self.king_board ^= piece_mask;
-                self.current_hash ^= tt.init_hash[m.end_index][PIECE_TYPE_KING_IDX];
-                self.current_hash ^= tt.init_hash[m.start_index][PIECE_TYPE_KING_IDX];
-                capturer_board = self.defender_board | self.king_board;
-                capturee_board = self.attacker_board;
-            }
+            PieceType::Attacker => self.attacker_board ^= piece_mask,
+            PieceType::Defender => self.defender_board ^= piece_mask,
+            PieceType::King => self.king_board ^= piece_mask,
         }
+    }
+
+    // Pieces captured by m, which has just been moved into place.
+    fn captures(&self, m: Move) -> Bitboard {
+        let capturee_board = match m.piece_type {
+            PieceType::Attacker => self.defender_board,
+            PieceType::Defender => self.attacker_board,
+            // an unarmed king cannot capture by moving
+            PieceType::King if self.rules.armed_king => self.attacker_board,
+            PieceType::King => EMPTY_BOARD,
+        };
+        let capturer_board = self.hammer_board(m.piece_type);
 
-        // check for captures
         let (end_row, end_col) = index_to_rc(m.end_index);
+        let mut captured = EMPTY_BOARD;
         for dir in DIRS.iter() {
             let capturee_row = end_row as isize + dir.0;
             let capturee_col = end_col as isize + dir.1;
             if valid_capture(
+                self.size(),
                 capturer_board,
                 capturee_board,
                 (end_row as isize, end_col as isize),
                 (capturee_row, capturee_col),
             ) {
-                let captured_index = rc_to_index(capturee_row as usize, capturee_col as usize);
-                let captured_piece_mask = 1 << captured_index;
-                match m.piece_type {
-                    PieceType::Attacker => {
-                        self.defender_board ^= captured_piece_mask;
-                        self.current_hash ^= tt.init_hash[captured_index][PIECE_TYPE_DEFENDER_IDX]
-                    }
-                    _ => {
-                        self.attacker_board ^= captured_piece_mask;
-                        self.current_hash ^= tt.init_hash[captured_index][PIECE_TYPE_ATTACKER_IDX];
-                    }
-                }
+                captured |=
+                    Bitboard::square(rc_to_index(capturee_row as usize, capturee_col as usize));
             }
         }
+        if self.rules.shieldwall && !capturee_board.is_empty() {
+            captured |= self.shieldwall_captures(m.end_index, m.piece_type);
+        }
+        captured
+    }
+
+    /// Pieces that a legal move would capture, without making it.
+    pub fn captures_of(&self, m: Move) -> Bitboard {
+        // only the pieces matter, so the history is left behind rather than copied
+        let mut after = Board {
+            history: Vec::new(),
+            position_counts: HashMap::new(),
+            ..*self
+        };
+        after.move_piece(m);
+        after.captures(m)
+    }
+
+    pub fn make_move(&mut self, m: Move, tt: &TranspositionTable) {
+        let mut hist_move = MoveHistoryElement {
+            m,
+            captured: EMPTY_BOARD,
+            result: self.result,
+            attacker_board: self.attacker_board,
+            defender_board: self.defender_board,
+            king_board: self.king_board,
+            current_hash: self.current_hash,
+            quiet_moves: self.quiet_moves,
+        };
+
+        self.move_piece(m);
+        let piece_idx = match m.piece_type {
+            PieceType::Attacker => PIECE_TYPE_ATTACKER_IDX,
+            PieceType::Defender => PIECE_TYPE_DEFENDER_IDX,
+            PieceType::King => PIECE_TYPE_KING_IDX,
+        };
+        self.current_hash ^= tt.init_hash[m.end_index][piece_idx];
+        self.current_hash ^= tt.init_hash[m.start_index][piece_idx];
+        let captured = self.captures(m);
+        hist_move.captured = captured;
+
+        let captured_type_idx = match m.piece_type {
+            PieceType::Attacker => {
+                self.defender_board ^= captured;
+                PIECE_TYPE_DEFENDER_IDX
+            }
+            _ => {
+                self.attacker_board ^= captured;
+                PIECE_TYPE_ATTACKER_IDX
+            }
+        };
+        for captured_index in captured {
+            self.current_hash ^= tt.init_hash[captured_index][captured_type_idx];
+        }
 
         // check for attacker win
-        if m.piece_type == PieceType::Attacker && self.king_captured() {
-            self.attacker_win = true;
+        if m.piece_type == PieceType::Attacker {
+            if self.king_captured(m.end_index) {
+                self.result = Some(Outcome::AttackersWin(Reason::KingCaptured));
+            } else if self.rules.encirclement && self.is_encircled() {
+                self.result = Some(Outcome::AttackersWin(Reason::Encirclement));
+            }
         }
 
         // check for defender win
-        if m.piece_type == PieceType::King {
-            // self.defender_win = (end_col == 0 || end_col == BOARD_SIZE - 1)
-            //     && (end_row == 0 || end_row == BOARD_SIZE - 1);
-            self.defender_win = end_col == 0 || end_col == BOARD_SIZE - 1 || end_row == 0 || end_row == BOARD_SIZE - 1;
+        if m.piece_type == PieceType::King && self.escape_board().contains(m.end_index) {
+            self.result = Some(Outcome::DefendersWin(Reason::KingEscaped));
+        } else if m.piece_type != PieceType::Attacker && self.rules.edge_fort && self.is_edge_fort()
+        {
+            self.result = Some(Outcome::DefendersWin(Reason::EdgeFort));
         }
 
-        if self.is_repetition() {
-            self.stalemate = true;
-        }
+        self.quiet_moves = if captured.is_empty() {
+            self.quiet_moves + 1
+        } else {
+            0
+        };
+        *self
+            .position_counts
+            .entry(hist_move.current_hash)
+            .or_insert(0) += 1;
         self.history.push(hist_move);
         self.attacker_move = !self.attacker_move;
         self.current_hash ^= tt.attacker_bits_seed; // toggles for attacker's turn
+
+        if self.result.is_none() {
+            self.result = self.draw_or_repetition_outcome();
+        }
+    }
+
+    /// Outcome of the repetition and move limit rules for the position just reached.
+    fn draw_or_repetition_outcome(&self) -> Option<Outcome> {
+        let repetitions = self.repetitions();
+        // the side that just moved is the one that repeated the position
+        let outcome = match self.rules.repetition {
+            Repetition::Draw(n) if repetitions >= n => Some(Outcome::Draw(Reason::Repetition)),
+            Repetition::Loss(n) if repetitions >= n && self.attacker_move => {
+                Some(Outcome::AttackersWin(Reason::Repetition))
+            }
+            Repetition::Loss(n) if repetitions >= n => {
+                Some(Outcome::DefendersWin(Reason::Repetition))
+            }
+            _ => None,
+        };
+        match self.rules.move_limit {
+            Some(limit) if outcome.is_none() && self.quiet_moves >= limit => {
+                Some(Outcome::Draw(Reason::MoveLimit))
+            }
+            _ => outcome,
+        }
+    }
+
+    pub fn last_move_info(&self) -> Option<MoveInfo> {
+        self.history.last().map(|h| MoveInfo {
+            m: h.m,
+            captured: h.captured,
+            result: self.result,
+        })
     }
 
     pub fn unmake_move(&mut self) {
@@ -251,63 +671,66 @@ impl Board {
         self.attacker_board = m.attacker_board;
         self.king_board = m.king_board;
         self.current_hash = m.current_hash;
+        self.quiet_moves = m.quiet_moves;
+        if let Some(count) = self.position_counts.get_mut(&m.current_hash) {
+            *count -= 1;
+            if *count == 0 {
+                self.position_counts.remove(&m.current_hash);
+            }
+        }
+        self.result = m.result;
 
-        self.attacker_win = false;
-        self.defender_win = false;
-        self.stalemate = false;
         self.attacker_move = !self.attacker_move;
     }
+}
 
-    pub fn to_string(&self) -> String {
-        let mut s = String::new();
-        for i in (0..BOARD_SIZE).rev() {
-            s.push((i + 1 + ('0' as usize)) as u8 as char);
-            s.push(' ');
-            for j in 0..BOARD_SIZE {
-                let index = rc_to_index(i as usize, j as usize);
-                if self.attacker_board & (1 << index) != 0 {
-                    s.push('V');
-                } else if self.king_board & (1 << index) != 0 {
-                    s.push('K');
-                } else if self.defender_board & (1 << index) != 0 {
-                    s.push('O');
-                } else if self.offlimits_board & (1 << index) != 0 {
-                    s.push('#');
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for i in (0..self.size()).rev() {
+            write!(f, "{:>2} ", i + 1)?;
+            for j in 0..self.size() {
+                let index = rc_to_index(i, j);
+                let c = if self.attacker_board.contains(index) {
+                    'V'
+                } else if self.king_board.contains(index) {
+                    'K'
+                } else if self.defender_board.contains(index) {
+                    'O'
+                } else if self.offlimits_board.contains(index) {
+                    '#'
                 } else {
-                    s.push('.');
-                }
-                s.push(' ');
+                    '.'
+                };
+                write!(f, "{} ", c)?;
             }
-            s.push('\n');
+            writeln!(f)?;
         }
 
-        s.push_str("  ");
-        for j in 0..BOARD_SIZE {
-            s.push((j + ('a' as usize)) as u8 as char);
-            s.push(' ');
+        write!(f, "   ")?;
+        for j in 0..self.size() {
+            write!(f, "{} ", (j as u8 + b'a') as char)?;
         }
-        s.push('\n');
-
-        return s;
+        writeln!(f)
     }
 }
 
 #[inline(always)]
 pub fn valid_capture(
+    size: usize,
     capturer_bitboard: Bitboard,
     capturee_bitboard: Bitboard,
     capturer_coords: (isize, isize),
     capturee_coords: (isize, isize),
 ) -> bool {
-    if !inbounds(capturee_coords.0, capturee_coords.1) {
+    if !inbounds(capturee_coords.0, capturee_coords.1, size) {
         return false;
     }
-    if !inbounds(capturer_coords.0, capturer_coords.1) {
+    if !inbounds(capturer_coords.0, capturer_coords.1, size) {
         return false;
     }
 
     let capturee_index = rc_to_index(capturee_coords.0 as usize, capturee_coords.1 as usize);
-    if capturee_bitboard & (1 << capturee_index) == 0 {
+    if !capturee_bitboard.contains(capturee_index) {
         return false;
     }
 
@@ -316,10 +739,448 @@ pub fn valid_capture(
         2 * capturee_coords.1 - capturer_coords.1,
     );
 
-    if !inbounds(ally_coords.0, ally_coords.1) {
+    if !inbounds(ally_coords.0, ally_coords.1, size) {
         return false;
     }
 
     let ally_index = rc_to_index(ally_coords.0 as usize, ally_coords.1 as usize);
-    capturer_bitboard & (1 << ally_index) != 0
+    capturer_bitboard.contains(ally_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::HostileThrone;
+
+    fn board_with(
+        rules: Rules,
+        attackers: &[(usize, usize)],
+        defenders: &[(usize, usize)],
+        king: (usize, usize),
+        attacker_move: bool,
+    ) -> Board {
+        let mut b = Board::new(rules);
+        b.attacker_board = EMPTY_BOARD;
+        b.defender_board = EMPTY_BOARD;
+        for &(row, col) in attackers {
+            b.attacker_board |= Bitboard::square(rc_to_index(row, col));
+        }
+        for &(row, col) in defenders {
+            b.defender_board |= Bitboard::square(rc_to_index(row, col));
+        }
+        b.king_board = Bitboard::square(rc_to_index(king.0, king.1));
+        b.attacker_move = attacker_move;
+        b
+    }
+
+    fn mv(piece_type: PieceType, from: (usize, usize), to: (usize, usize)) -> Move {
+        Move {
+            start_index: rc_to_index(from.0, from.1),
+            end_index: rc_to_index(to.0, to.1),
+            piece_type,
+        }
+    }
+
+    #[test]
+    fn attacker_captured_against_corner() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::brandubh(), &[(0, 1)], &[(2, 2)], (3, 3), false);
+        b.make_move(mv(PieceType::Defender, (2, 2), (0, 2)), &tt);
+        assert!(b.attacker_board.is_empty());
+    }
+
+    #[test]
+    fn defender_captured_against_corner() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::brandubh(), &[(2, 3)], &[(1, 0)], (3, 3), true);
+        b.make_move(mv(PieceType::Attacker, (2, 3), (2, 0)), &tt);
+        assert!(b.defender_board.is_empty());
+    }
+
+    #[test]
+    fn corners_not_hostile_without_rule() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::ard_ri(), &[(0, 1)], &[(1, 2)], (3, 3), false);
+        b.make_move(mv(PieceType::Defender, (1, 2), (0, 2)), &tt);
+        assert_eq!(b.attacker_board.count_ones(), 1);
+    }
+
+    #[test]
+    fn defender_captured_against_empty_throne() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::brandubh(), &[(3, 6)], &[(3, 4)], (5, 5), true);
+        b.make_move(mv(PieceType::Attacker, (3, 6), (3, 5)), &tt);
+        assert!(b.defender_board.is_empty());
+    }
+
+    #[test]
+    fn defender_safe_next_to_occupied_throne() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::fetlar(), &[(5, 8)], &[(5, 6)], (5, 5), true);
+        b.make_move(mv(PieceType::Attacker, (5, 8), (5, 7)), &tt);
+        assert_eq!(b.defender_board.count_ones(), 1);
+    }
+
+    #[test]
+    fn attacker_captured_against_occupied_throne() {
+        let tt = TranspositionTable::new();
+        let rules = Rules {
+            armed_king: false,
+            hostile_throne: HostileThrone::Always,
+            ..Rules::fetlar()
+        };
+        let mut b = board_with(rules, &[(5, 6)], &[(8, 7)], (5, 5), false);
+        b.make_move(mv(PieceType::Defender, (8, 7), (5, 7)), &tt);
+        assert!(b.attacker_board.is_empty());
+
+        let rules = Rules {
+            hostile_throne: HostileThrone::WhenEmpty,
+            ..rules
+        };
+        let mut b = board_with(rules, &[(5, 6)], &[(8, 7)], (5, 5), false);
+        b.make_move(mv(PieceType::Defender, (8, 7), (5, 7)), &tt);
+        assert_eq!(b.attacker_board.count_ones(), 1);
+    }
+
+    #[test]
+    fn attacker_captured_against_empty_throne() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::tablut(), &[(4, 5)], &[(7, 6)], (0, 4), false);
+        b.make_move(mv(PieceType::Defender, (7, 6), (4, 6)), &tt);
+        assert!(b.attacker_board.is_empty());
+
+        let rules = Rules {
+            hostile_throne: HostileThrone::Never,
+            ..Rules::tablut()
+        };
+        let mut b = board_with(rules, &[(4, 5)], &[(7, 6)], (0, 4), false);
+        b.make_move(mv(PieceType::Defender, (7, 6), (4, 6)), &tt);
+        assert_eq!(b.attacker_board.count_ones(), 1);
+    }
+
+    #[test]
+    fn king_surrounded_on_four_sides() {
+        let tt = TranspositionTable::new();
+        let attackers = [(2, 3), (3, 2), (3, 4), (4, 8)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &[], (3, 3), true);
+        b.make_move(mv(PieceType::Attacker, (4, 8), (4, 3)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+
+        let mut b = board_with(Rules::copenhagen(), &attackers[1..], &[], (3, 3), true);
+        b.make_move(mv(PieceType::Attacker, (4, 8), (4, 3)), &tt);
+        assert_eq!(b.result, None);
+    }
+
+    #[test]
+    fn king_next_to_throne_captured_on_three_sides() {
+        let tt = TranspositionTable::new();
+        let attackers = [(4, 4), (4, 6), (3, 8)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &[], (4, 5), true);
+        b.make_move(mv(PieceType::Attacker, (3, 8), (3, 5)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+    }
+
+    #[test]
+    fn king_on_edge() {
+        let tt = TranspositionTable::new();
+        let attackers = [(0, 3), (0, 5), (5, 4)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &[], (0, 4), true);
+        b.make_move(mv(PieceType::Attacker, (5, 4), (1, 4)), &tt);
+        assert_eq!(b.result, None);
+
+        let rules = Rules {
+            king_captured_on_edge: true,
+            ..Rules::copenhagen()
+        };
+        let mut b = board_with(rules, &attackers, &[], (0, 4), true);
+        b.make_move(mv(PieceType::Attacker, (5, 4), (1, 4)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+    }
+
+    #[test]
+    fn throne_dependent_king_capture() {
+        let tt = TranspositionTable::new();
+
+        // away from the throne two attackers are enough
+        let mut b = board_with(Rules::tablut(), &[(2, 1), (2, 8)], &[], (2, 2), true);
+        b.make_move(mv(PieceType::Attacker, (2, 8), (2, 3)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+
+        // next to the throne the king needs attackers on the other three sides
+        let mut b = board_with(Rules::tablut(), &[(3, 3), (3, 8)], &[], (3, 4), true);
+        b.make_move(mv(PieceType::Attacker, (3, 8), (3, 5)), &tt);
+        assert_eq!(b.result, None);
+        let mut b = board_with(
+            Rules::tablut(),
+            &[(3, 3), (2, 8), (3, 8)],
+            &[],
+            (3, 4),
+            true,
+        );
+        b.make_move(mv(PieceType::Attacker, (2, 8), (2, 4)), &tt);
+        b.make_move(mv(PieceType::Attacker, (3, 8), (3, 5)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+
+        // on the throne it takes all four
+        let attackers = [(4, 3), (4, 5), (3, 4), (5, 8)];
+        let mut b = board_with(Rules::tablut(), &attackers[1..], &[], (4, 4), true);
+        b.make_move(mv(PieceType::Attacker, (5, 8), (5, 4)), &tt);
+        assert_eq!(b.result, None);
+        let mut b = board_with(Rules::tablut(), &attackers, &[], (4, 4), true);
+        b.make_move(mv(PieceType::Attacker, (5, 8), (5, 4)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::KingCaptured)));
+    }
+
+    #[test]
+    fn shieldwall_capture() {
+        let tt = TranspositionTable::new();
+        let attackers = [(0, 2), (1, 3), (1, 4), (1, 5), (4, 6)];
+        let defenders = [(0, 3), (0, 4), (0, 5)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &defenders, (5, 5), true);
+        b.rehash(&tt);
+        let before = b.clone();
+
+        b.make_move(mv(PieceType::Attacker, (4, 6), (0, 6)), &tt);
+        assert!(b.defender_board.is_empty());
+        assert_eq!(b.current_hash, tt.hash_from_board(&b));
+
+        b.unmake_move();
+        assert_eq!(b.defender_board, before.defender_board);
+        assert_eq!(b.current_hash, before.current_hash);
+    }
+
+    #[test]
+    fn shieldwall_needs_every_piece_faced() {
+        let tt = TranspositionTable::new();
+        let attackers = [(0, 2), (1, 3), (1, 5), (4, 6)];
+        let defenders = [(0, 3), (0, 4), (0, 5)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &defenders, (5, 5), true);
+        b.make_move(mv(PieceType::Attacker, (4, 6), (0, 6)), &tt);
+        assert_eq!(b.defender_board.count_ones(), 3);
+    }
+
+    #[test]
+    fn no_shieldwall_without_rule() {
+        let tt = TranspositionTable::new();
+        let attackers = [(0, 2), (1, 3), (1, 4), (1, 5), (4, 6)];
+        let defenders = [(0, 3), (0, 4), (0, 5)];
+        let mut b = board_with(Rules::fetlar(), &attackers, &defenders, (5, 5), true);
+        b.make_move(mv(PieceType::Attacker, (4, 6), (0, 6)), &tt);
+        assert_eq!(b.defender_board.count_ones(), 3);
+    }
+
+    #[test]
+    fn shieldwall_against_corner() {
+        let tt = TranspositionTable::new();
+        let attackers = [(1, 9), (2, 9), (6, 10)];
+        let defenders = [(1, 10), (2, 10)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &defenders, (8, 8), true);
+        b.make_move(mv(PieceType::Attacker, (6, 10), (3, 10)), &tt);
+        assert!(b.defender_board.is_empty());
+    }
+
+    #[test]
+    fn shieldwall_spares_king() {
+        let tt = TranspositionTable::new();
+        let attackers = [(1, 9), (2, 9), (3, 9), (6, 10)];
+        let defenders = [(1, 10), (3, 10)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &defenders, (2, 10), true);
+        b.make_move(mv(PieceType::Attacker, (6, 10), (4, 10)), &tt);
+        assert!(b.defender_board.is_empty());
+        assert_eq!(b.king_index(), rc_to_index(2, 10));
+    }
+
+    #[test]
+    fn edge_fort_wins() {
+        let tt = TranspositionTable::new();
+        let defenders = [(0, 2), (0, 6), (1, 3), (1, 4), (4, 5)];
+        let mut b = board_with(Rules::copenhagen(), &[(8, 8)], &defenders, (0, 4), false);
+        assert!(!b.is_edge_fort());
+        b.make_move(mv(PieceType::Defender, (4, 5), (1, 5)), &tt);
+        assert_eq!(b.result, Some(Outcome::DefendersWin(Reason::EdgeFort)));
+
+        b.unmake_move();
+        b.rules.edge_fort = false;
+        b.make_move(mv(PieceType::Defender, (4, 5), (1, 5)), &tt);
+        assert_eq!(b.result, None);
+    }
+
+    #[test]
+    fn edge_fort_along_corner() {
+        // D D D
+        // D . .
+        // D . K
+        // D D #
+        let defenders = [(0, 8), (0, 9), (1, 8), (2, 8), (3, 8), (3, 9), (3, 10)];
+        let b = board_with(Rules::copenhagen(), &[(8, 8)], &defenders, (1, 10), false);
+        assert!(b.is_edge_fort());
+    }
+
+    #[test]
+    fn edge_fort_needs_king_on_edge_and_room_to_move() {
+        let defenders = [(1, 3), (1, 4), (1, 5), (2, 3), (2, 5), (3, 4)];
+        let b = board_with(Rules::copenhagen(), &[(8, 8)], &defenders, (2, 4), false);
+        assert!(!b.is_edge_fort());
+
+        let defenders = [(0, 3), (0, 5), (1, 4)];
+        let b = board_with(Rules::copenhagen(), &[(8, 8)], &defenders, (0, 4), false);
+        assert!(!b.is_edge_fort());
+    }
+
+    #[test]
+    fn breakable_edge_fort() {
+        // the top defender can be sandwiched from either side
+        let defenders = [(0, 2), (0, 6), (1, 3), (1, 5), (2, 4)];
+        let b = board_with(Rules::copenhagen(), &[(8, 8)], &defenders, (0, 4), false);
+        assert!(!b.is_edge_fort());
+
+        // an attacker trapped inside the fort
+        let defenders = [(0, 2), (0, 6), (1, 3), (1, 4), (1, 5)];
+        let b = board_with(Rules::copenhagen(), &[(0, 3)], &defenders, (0, 4), false);
+        assert!(!b.is_edge_fort());
+    }
+
+    #[test]
+    fn encirclement_wins() {
+        let tt = TranspositionTable::new();
+        let attackers = [(4, 5), (4, 6), (5, 4), (6, 5), (6, 6), (5, 10)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &[(5, 6)], (5, 5), true);
+        assert!(!b.is_encircled());
+        b.make_move(mv(PieceType::Attacker, (5, 10), (5, 7)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::Encirclement)));
+    }
+
+    #[test]
+    fn encirclement_must_include_every_defender() {
+        let tt = TranspositionTable::new();
+        let attackers = [(4, 5), (4, 6), (5, 4), (6, 5), (6, 6), (5, 10)];
+        let defenders = [(5, 6), (1, 1)];
+        let mut b = board_with(Rules::copenhagen(), &attackers, &defenders, (5, 5), true);
+        b.make_move(mv(PieceType::Attacker, (5, 10), (5, 7)), &tt);
+        assert_eq!(b.result, None);
+    }
+
+    #[test]
+    fn no_legal_moves() {
+        let attackers = [(0, 1)];
+        let defenders = [(0, 2), (1, 1)];
+        let b = board_with(Rules::copenhagen(), &attackers, &defenders, (5, 5), true);
+        assert_eq!(
+            b.outcome(),
+            Some(Outcome::DefendersWin(Reason::NoLegalMoves))
+        );
+
+        let b = board_with(Rules::ard_ri(), &attackers, &defenders, (3, 3), true);
+        assert_eq!(b.outcome(), Some(Outcome::Draw(Reason::NoLegalMoves)));
+
+        let b = board_with(Rules::ard_ri(), &attackers, &defenders, (3, 3), false);
+        assert_eq!(b.outcome(), None);
+    }
+
+    // attacker shuffles along the top row, defender along the bottom row
+    fn shuffle(b: &mut Board, tt: &TranspositionTable, plies: usize) {
+        let moves = [
+            mv(PieceType::Attacker, (1, 0), (1, 1)),
+            mv(PieceType::Defender, (5, 6), (5, 5)),
+            mv(PieceType::Attacker, (1, 1), (1, 0)),
+            mv(PieceType::Defender, (5, 5), (5, 6)),
+        ];
+        for m in moves.iter().cycle().take(plies) {
+            assert_eq!(b.result, None);
+            b.make_move(*m, tt);
+        }
+    }
+
+    #[test]
+    fn threefold_repetition_draws() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::brandubh(), &[(1, 0)], &[(5, 6)], (3, 3), true);
+        shuffle(&mut b, &tt, 7);
+        assert_eq!(b.repetitions(), 2);
+        assert_eq!(b.result, None);
+        b.make_move(mv(PieceType::Defender, (5, 5), (5, 6)), &tt);
+        assert_eq!(b.repetitions(), 3);
+        assert_eq!(b.result, Some(Outcome::Draw(Reason::Repetition)));
+
+        b.unmake_move();
+        assert_eq!(b.repetitions(), 2);
+        while !b.history.is_empty() {
+            b.unmake_move();
+        }
+        assert_eq!(b.repetitions(), 1);
+        assert!(b.position_counts.is_empty());
+    }
+
+    #[test]
+    fn first_repetition_draws_in_ard_ri() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::ard_ri(), &[(1, 0)], &[(5, 6)], (3, 3), true);
+        shuffle(&mut b, &tt, 4);
+        assert_eq!(b.result, Some(Outcome::Draw(Reason::Repetition)));
+    }
+
+    #[test]
+    fn perpetual_repetition_loses() {
+        let tt = TranspositionTable::new();
+        let mut b = board_with(Rules::copenhagen(), &[(1, 0)], &[(5, 6)], (8, 8), true);
+        shuffle(&mut b, &tt, 7);
+        assert_eq!(b.result, None);
+        // the defenders bring back the starting position for the third time
+        b.make_move(mv(PieceType::Defender, (5, 5), (5, 6)), &tt);
+        assert_eq!(b.result, Some(Outcome::AttackersWin(Reason::Repetition)));
+    }
+
+    #[test]
+    fn capture_resets_move_limit() {
+        let tt = TranspositionTable::new();
+        let rules = Rules {
+            move_limit: Some(6),
+            ..Rules::brandubh()
+        };
+        let mut b = board_with(rules, &[(1, 0), (4, 2)], &[(5, 6), (4, 1)], (3, 3), true);
+        shuffle(&mut b, &tt, 2);
+        b.make_move(mv(PieceType::Attacker, (1, 0), (4, 0)), &tt);
+        assert!(!b.defender_board.contains(rc_to_index(4, 1)));
+        assert_eq!(b.quiet_moves, 0);
+
+        b.make_move(mv(PieceType::Defender, (5, 5), (5, 6)), &tt);
+        b.make_move(mv(PieceType::Attacker, (4, 0), (1, 0)), &tt);
+        shuffle(&mut b, &tt, 3);
+        assert_eq!(b.quiet_moves, 5);
+        assert_eq!(b.result, None);
+        b.make_move(mv(PieceType::Defender, (5, 5), (5, 6)), &tt);
+        assert_eq!(b.result, Some(Outcome::Draw(Reason::MoveLimit)));
+
+        b.unmake_move();
+        assert_eq!(b.quiet_moves, 5);
+        assert_eq!(b.result, None);
+    }
+
+    #[test]
+    fn last_move_info_and_unmake_restore_results() {
+        let tt = TranspositionTable::new();
+        let attackers = [(0, 1), (3, 6)];
+        let mut b = board_with(Rules::brandubh(), &attackers, &[(1, 2)], (6, 3), false);
+        assert_eq!(b.last_move_info(), None);
+
+        let capture = mv(PieceType::Defender, (1, 2), (0, 2));
+        b.make_move(capture, &tt);
+        let info = b.last_move_info().unwrap();
+        assert_eq!(info.m, capture);
+        assert_eq!(info.captured, Bitboard::square(rc_to_index(0, 1)));
+        assert_eq!(info.result, None);
+
+        b.make_move(mv(PieceType::Attacker, (3, 6), (2, 6)), &tt);
+        b.make_move(mv(PieceType::King, (6, 3), (6, 0)), &tt);
+        let escaped = Some(Outcome::DefendersWin(Reason::KingEscaped));
+        assert_eq!(b.last_move_info().unwrap().result, escaped);
+        assert!(b.last_move_info().unwrap().captured.is_empty());
+
+        // moves played past the end of the game keep the result, and unmaking them restores it
+        b.make_move(mv(PieceType::Attacker, (2, 6), (3, 6)), &tt);
+        b.unmake_move();
+        assert_eq!(b.result, escaped);
+        b.unmake_move();
+        assert_eq!(b.result, None);
+    }
 }
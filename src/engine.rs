@@ -1,121 +1,465 @@
-use crate::board::{Board, Move, NULL_MOVE};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::{Board, Move, Outcome, NULL_MOVE};
 use crate::eval::naive_eval;
-use crate::movegen::MoveGenerator;
+use crate::limits::{SearchLimits, TimeManager};
+use crate::movegen::{escape_routes, MoveGenerator};
 use crate::ttable::{Flag, TranspositionTable};
 
 const ASPIRATION_WINDOW: i16 = 300;
+// Plies of captures and escape threats searched beyond the nominal depth.
+const QUIESCENCE_DEPTH: u8 = 8;
+const WIN_SCORE: i16 = 10000;
 
 pub struct TaflAI {
-    pub max_depth: u8,
+    pub limits: SearchLimits,
+    /// Number of best root moves to score and report. Above 1, every root move is searched
+    /// to an exact score, which is slower.
+    pub multi_pv: usize,
     pub ttable: TranspositionTable,
 }
 
 impl TaflAI {
+    /// Searches with iterative deepening until a limit is reached. The result always comes
+    /// from the deepest iteration that finished, unless the first one was cut short.
     pub fn find_best_move(&mut self, b: &mut Board) -> EngineRecommendation {
-        let mut nnodes = 1;
-        let mut best_move = NULL_MOVE;
-        let mut best_eval = i16::MIN + 1;
+        self.search(b, &AtomicBool::new(false), |_| {})
+    }
+
+    /// `find_best_move` that also stops once `stop` is set, and reports each finished
+    /// iteration to `on_iteration`.
+    pub fn search(
+        &mut self,
+        b: &mut Board,
+        stop: &AtomicBool,
+        mut on_iteration: impl FnMut(&SearchInfo),
+    ) -> EngineRecommendation {
+        let mut state = SearchState {
+            tm: TimeManager::new(&self.limits, stop),
+            pv: PvTable::new(b),
+        };
+        // best first, scored for the side to move
+        let mut lines: Vec<RootLine> = Vec::new();
+        let mut completed_depth = 0;
         let color = if b.attacker_move { 1 } else { -1 };
 
         let mut root_moves = MoveGenerator::new(b).cached_moves;
-        for current_depth in 1..=self.max_depth {   
-            let mut nnodes_this_iter = 0;
-            let mut best_move_this_iter = NULL_MOVE;
-            let mut best_eval_this_iter = i16::MIN;
-            let mut alpha = best_eval.saturating_sub(ASPIRATION_WINDOW);
-            let mut beta = best_eval.saturating_add(ASPIRATION_WINDOW);
-
-            if current_depth > 1 {
-                if let Some(pos) = root_moves.iter().position(|&m| m == best_move) {
-                    let pv_move = root_moves.remove(pos);
-                    root_moves.insert(0, pv_move);
+        if root_moves.is_empty() {
+            return EngineRecommendation::new(&lines, 0, state.tm.nodes + 1, color);
+        }
+        for current_depth in 1..=self.limits.max_depth() {
+            // search last iteration's best moves first, in order
+            for line in lines.iter().rev() {
+                if let Some(pos) = root_moves.iter().position(|&m| m == line.m) {
+                    let m = root_moves.remove(pos);
+                    root_moves.insert(0, m);
                 }
             }
 
-            loop {
-                for &m in root_moves.iter() {
-                    b.make_move(m, &self.ttable);
-                    let eval = -negamax(
-                        self,
-                        b,
-                        current_depth - 1,
-                        &mut nnodes_this_iter,
-                        alpha,
-                        beta,
-                        -color,
-                    );
-                    b.unmake_move();
+            let lines_this_iter = if self.multi_pv > 1 {
+                self.search_each_root_move(b, &root_moves, current_depth, &lines, &mut state, color)
+            } else {
+                let previous = lines.first().map_or(i16::MIN + 1, |line| line.eval);
+                self.search_best_root_move(
+                    b,
+                    &root_moves,
+                    current_depth,
+                    previous,
+                    &mut state,
+                    color,
+                )
+            };
 
-                    if eval > best_eval_this_iter {
-                        best_eval_this_iter = eval;
-                        best_move_this_iter = m;
+            if state.tm.stopped() {
+                // an unfinished first iteration is still better than no move at all
+                if completed_depth == 0 {
+                    lines = lines_this_iter;
+                    if lines.is_empty() {
+                        lines.push(RootLine::unsearched(root_moves[0]));
+                    }
+                    for line in lines.iter_mut() {
+                        line.pv.truncate(1);
                     }
                 }
+                break;
+            }
 
-                if best_eval_this_iter <= alpha {
-                    alpha = i16::MIN + 1;
-                } else if best_eval_this_iter >= beta {
-                    beta = i16::MAX - 1;
-                } else {
+            lines = lines_this_iter;
+            for line in lines.iter_mut().take(self.multi_pv.max(1)) {
+                line.pv = legal_prefix(b, &line.pv, &self.ttable);
+            }
+            completed_depth = current_depth;
+            let elapsed = state.tm.elapsed();
+            let recommendation =
+                EngineRecommendation::new(&lines[..self.reported(&lines)], 0, 0, color);
+            on_iteration(&SearchInfo {
+                depth: current_depth,
+                evaluation: recommendation.evaluation,
+                nodes: state.tm.nodes + 1,
+                nps: ((state.tm.nodes + 1) as f64 / elapsed.as_secs_f64()) as u64,
+                elapsed,
+                pv: recommendation.pv,
+                lines: recommendation.lines,
+            });
+
+            if !state.tm.can_deepen() {
+                break;
+            }
+        }
+
+        let reported = self.reported(&lines);
+        EngineRecommendation::new(
+            &lines[..reported],
+            completed_depth,
+            state.tm.nodes + 1,
+            color,
+        )
+    }
+
+    // Number of lines to report out of those found.
+    fn reported(&self, lines: &[RootLine]) -> usize {
+        lines.len().min(self.multi_pv.max(1))
+    }
+
+    // Searches the root moves with one shared aspiration window, which is enough to find the
+    // best of them but leaves the others with only a bound.
+    fn search_best_root_move(
+        &mut self,
+        b: &mut Board,
+        root_moves: &[Move],
+        depth: u8,
+        previous: i16,
+        state: &mut SearchState,
+        color: i16,
+    ) -> Vec<RootLine> {
+        // kept off i16::MIN so that the window can be negated for the replies
+        let mut alpha = previous.saturating_sub(ASPIRATION_WINDOW).max(i16::MIN + 1);
+        let mut beta = previous.saturating_add(ASPIRATION_WINDOW);
+        loop {
+            // a pass that failed low only left upper bounds, which mustn't outrank exact scores
+            let mut best: Option<RootLine> = None;
+            // later moves only have to show they're no better than the best so far
+            let mut best_so_far = alpha;
+            for &m in root_moves {
+                b.make_move(m, &self.ttable);
+                let eval = -negamax(self, b, depth - 1, state, -beta, -best_so_far, -color);
+                b.unmake_move();
+                if state.tm.stopped() {
+                    return best.into_iter().collect();
+                }
+
+                if best.as_ref().is_none_or(|best| eval > best.eval) {
+                    best = Some(RootLine {
+                        m,
+                        eval,
+                        pv: state.pv.line_after(0, m),
+                    });
+                }
+                best_so_far = best_so_far.max(eval);
+                if best_so_far >= beta {
                     break;
                 }
             }
 
-            nnodes += nnodes_this_iter;
-            best_eval = best_eval_this_iter;
-            best_move = best_move_this_iter;
+            let best_eval = best.as_ref().map_or(i16::MIN, |best| best.eval);
+            if best_eval <= alpha {
+                alpha = i16::MIN + 1;
+            } else if best_eval >= beta {
+                beta = i16::MAX - 1;
+            } else {
+                return best.into_iter().collect();
+            }
         }
+    }
 
-        EngineRecommendation {
-            evaluation: best_eval * color,
-            best_move: best_move,
-            nnodes: nnodes,
+    // Searches every root move with its own aspiration window around its last score, so each
+    // gets an exact score, and ranks them.
+    fn search_each_root_move(
+        &mut self,
+        b: &mut Board,
+        root_moves: &[Move],
+        depth: u8,
+        previous: &[RootLine],
+        state: &mut SearchState,
+        color: i16,
+    ) -> Vec<RootLine> {
+        let mut lines = Vec::new();
+        for &m in root_moves {
+            let (mut alpha, mut beta) = match previous.iter().find(|line| line.m == m) {
+                Some(line) => (
+                    line.eval
+                        .saturating_sub(ASPIRATION_WINDOW)
+                        .max(i16::MIN + 1),
+                    line.eval
+                        .saturating_add(ASPIRATION_WINDOW)
+                        .min(i16::MAX - 1),
+                ),
+                None => (i16::MIN + 1, i16::MAX - 1),
+            };
+            b.make_move(m, &self.ttable);
+            let eval = loop {
+                let eval = -negamax(self, b, depth - 1, state, -beta, -alpha, -color);
+                if state.tm.stopped() {
+                    break eval;
+                }
+                if eval <= alpha && alpha > i16::MIN + 1 {
+                    alpha = i16::MIN + 1;
+                } else if eval >= beta && beta < i16::MAX - 1 {
+                    beta = i16::MAX - 1;
+                } else {
+                    break eval;
+                }
+            };
+            b.unmake_move();
+            if state.tm.stopped() {
+                break;
+            }
+            lines.push(RootLine {
+                m,
+                eval,
+                pv: state.pv.line_after(0, m),
+            });
         }
+        // a stable sort keeps the move ordering between equal scores
+        lines.sort_by_key(|line| std::cmp::Reverse(line.eval));
+        lines
+    }
+
+    /// Starts a search of `b` on a worker thread. The engine is handed back, along with its
+    /// recommendation, when the search is joined.
+    pub fn spawn_search(mut self, mut b: Board) -> SearchHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, info) = mpsc::channel();
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let rec = self.search(&mut b, &thread_stop, |i| {
+                // nobody listening is no reason to stop searching
+                let _ = sender.send(i.clone());
+            });
+            (self, rec)
+        });
+        SearchHandle { stop, info, thread }
     }
 }
 
+/// A search running on another thread.
+pub struct SearchHandle {
+    stop: Arc<AtomicBool>,
+    /// Receives a report after each finished iteration. Disconnects when the search ends.
+    pub info: Receiver<SearchInfo>,
+    thread: JoinHandle<(TaflAI, EngineRecommendation)>,
+}
+
+impl SearchHandle {
+    /// Asks the search to finish as soon as possible.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// The flag behind `stop`, for stopping the search from elsewhere.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the search to end.
+    pub fn join(self) -> (TaflAI, EngineRecommendation) {
+        self.thread.join().expect("search thread panicked")
+    }
+}
+
+/// Progress of a search after one iteration.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u8,
+    /// Evaluation from the attackers' point of view.
+    pub evaluation: i16,
+    pub nodes: usize,
+    pub nps: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+    /// The best `multi_pv` root moves, best first.
+    pub lines: Vec<PvLine>,
+}
+
+/// A root move with its score and the line expected to follow.
+#[derive(Clone, Debug)]
+pub struct PvLine {
+    pub m: Move,
+    /// Evaluation from the attackers' point of view.
+    pub evaluation: i16,
+    /// The line starting with `m`.
+    pub pv: Vec<Move>,
+}
+
 pub struct EngineRecommendation {
     pub evaluation: i16,
     pub best_move: Move,
+    /// The line the engine expects, starting with `best_move`. It can be cut short where the
+    /// search relied on the transposition table.
+    pub pv: Vec<Move>,
+    /// The best `multi_pv` root moves, best first. The first is the one above.
+    pub lines: Vec<PvLine>,
+    /// Depth of the last iteration that finished.
+    pub depth: u8,
     pub nnodes: usize,
 }
 
+impl EngineRecommendation {
+    fn new(lines: &[RootLine], depth: u8, nnodes: usize, color: i16) -> Self {
+        let lines: Vec<PvLine> = lines
+            .iter()
+            .map(|line| PvLine {
+                m: line.m,
+                evaluation: line.eval * color,
+                pv: line.pv.clone(),
+            })
+            .collect();
+        let (evaluation, best_move, pv) = match lines.first() {
+            Some(best) => (best.evaluation, best.m, best.pv.clone()),
+            None => ((i16::MIN + 1) * color, NULL_MOVE, Vec::new()),
+        };
+        EngineRecommendation {
+            evaluation,
+            best_move,
+            pv,
+            lines,
+            depth,
+            nnodes,
+        }
+    }
+}
+
 pub struct EngineBenchmark {
     pub recommendation: EngineRecommendation,
     pub elapsed: std::time::Duration,
 }
 
+// A root move scored for the side to move.
+struct RootLine {
+    m: Move,
+    eval: i16,
+    pv: Vec<Move>,
+}
+
+impl RootLine {
+    fn unsearched(m: Move) -> Self {
+        RootLine {
+            m,
+            eval: i16::MIN + 1,
+            pv: vec![m],
+        }
+    }
+}
+
+// What a search carries between nodes besides the board.
+struct SearchState<'a> {
+    tm: TimeManager<'a>,
+    pv: PvTable,
+}
+
+// Triangular table of principal variations: row `ply` holds the best line found below the
+// node last searched at that distance from the root.
+struct PvTable {
+    root_ply: usize,
+    lines: Vec<Vec<Move>>,
+}
+
+impl PvTable {
+    fn new(root: &Board) -> Self {
+        PvTable {
+            root_ply: root.history.len(),
+            lines: Vec::new(),
+        }
+    }
+
+    fn ply(&self, b: &Board) -> usize {
+        b.history.len() - self.root_ply
+    }
+
+    fn clear(&mut self, ply: usize) {
+        if self.lines.len() <= ply + 1 {
+            self.lines.resize_with(ply + 2, Vec::new);
+        }
+        self.lines[ply].clear();
+    }
+
+    // Makes `m` followed by the line below it the best line at `ply`.
+    fn update(&mut self, ply: usize, m: Move) {
+        let (above, below) = self.lines.split_at_mut(ply + 1);
+        above[ply].clear();
+        above[ply].push(m);
+        above[ply].extend_from_slice(&below[0]);
+    }
+
+    // `m` followed by the line below it, for a move at `ply` that isn't kept in the table.
+    fn line_after(&self, ply: usize, m: Move) -> Vec<Move> {
+        let mut line = vec![m];
+        if let Some(below) = self.lines.get(ply + 1) {
+            line.extend_from_slice(below);
+        }
+        line
+    }
+}
+
+// The longest start of `line` that can be played from `b`. A line built from a transposition
+// table hit on a colliding hash could otherwise contain a move that's illegal in sequence.
+fn legal_prefix(b: &mut Board, line: &[Move], tt: &TranspositionTable) -> Vec<Move> {
+    let mut legal = Vec::new();
+    for &m in line {
+        if b.result.is_some() || !MoveGenerator::new(b).any(|legal_move| legal_move == m) {
+            break;
+        }
+        b.make_move(m, tt);
+        legal.push(m);
+    }
+    for _ in 0..legal.len() {
+        b.unmake_move();
+    }
+    legal
+}
+
 fn negamax(
     tafl_ai: &mut TaflAI,
     b: &mut Board,
     depth: u8,
-    nnodes: &mut usize,
+    state: &mut SearchState,
     mut alpha: i16,
     beta: i16,
     color: i16,
 ) -> i16 {
-    *nnodes += 1;
     if depth == 0 {
-        return naive_eval(b) * color;
+        return quiescence(tafl_ai, b, state, alpha, beta, color, QUIESCENCE_DEPTH);
     }
 
-    if b.attacker_win {
-        return (10000 + depth as i16) * color;
-    } else if b.defender_win {
-        return (-10000 - depth as i16) * color;
+    state.tm.nodes += 1;
+    if state.tm.should_stop() {
+        return 0;
+    }
+    let ply = state.pv.ply(b);
+    state.pv.clear(ply);
+    if let Some(outcome) = b.result {
+        return outcome_eval(outcome, depth) * color;
     }
 
     let original_alpha = alpha;
     if let Some(entry) = tafl_ai.ttable.retrieve(b) {
         if entry.depth >= depth {
             match entry.flag {
-                Flag::EXACT => return entry.evaluation,
-                Flag::LOWERBOUND => {
+                Flag::Exact => return entry.evaluation,
+                Flag::LowerBound => {
                     if entry.evaluation >= beta {
                         return entry.evaluation;
                     }
                 }
-                Flag::UPPERBOUND => {
+                Flag::UpperBound => {
                     if entry.evaluation <= alpha {
                         return entry.evaluation;
                     }
@@ -125,13 +469,24 @@ fn negamax(
     }
 
     let moves = MoveGenerator::new(b);
+    if moves.cached_moves.is_empty() {
+        return outcome_eval(b.no_moves_outcome(), depth) * color;
+    }
+
     let mut value = i16::MIN;
     for mv in moves {
         b.make_move(mv, &tafl_ai.ttable);
-        let eval = -negamax(tafl_ai, b, depth - 1, nnodes, -beta, -alpha, -color);
+        let eval = -negamax(tafl_ai, b, depth - 1, state, -beta, -alpha, -color);
         b.unmake_move();
+        // the value of an abandoned subtree is meaningless, so it mustn't reach the table
+        if state.tm.stopped() {
+            return 0;
+        }
 
-        value = value.max(eval);
+        if eval > value {
+            value = eval;
+            state.pv.update(ply, mv);
+        }
         alpha = alpha.max(value);
         if alpha >= beta {
             break;
@@ -139,14 +494,422 @@ fn negamax(
     }
 
     let flag = if value <= original_alpha {
-        Flag::UPPERBOUND
+        Flag::UpperBound
     } else if value >= beta {
-        Flag::LOWERBOUND
+        Flag::LowerBound
     } else {
-        Flag::EXACT
+        Flag::Exact
     };
 
     tafl_ai.ttable.store(b, value, depth, flag);
 
-    return value;
+    value
+}
+
+// Searches captures and king escape threats until the position settles, so it isn't judged in
+// the middle of an exchange or a race to the edge. The side to move may stand pat on the
+// static evaluation instead, unless the king threatens to escape against the attackers.
+fn quiescence(
+    tafl_ai: &mut TaflAI,
+    b: &mut Board,
+    state: &mut SearchState,
+    mut alpha: i16,
+    beta: i16,
+    color: i16,
+    depth: u8,
+) -> i16 {
+    state.tm.nodes += 1;
+    if state.tm.should_stop() {
+        return 0;
+    }
+    let ply = state.pv.ply(b);
+    state.pv.clear(ply);
+    if let Some(outcome) = b.result {
+        return outcome_eval(outcome, 0) * color;
+    }
+
+    let routes = escape_routes(b);
+    if !routes.is_empty() && !b.attacker_move {
+        // the king escapes on this move
+        return WIN_SCORE;
+    }
+    let stand_pat = naive_eval(b) * color;
+    if depth == 0 {
+        return stand_pat;
+    }
+    let threatened = !routes.is_empty();
+    let mut value = if threatened {
+        // unless a block or capture is found, the king gets away
+        -WIN_SCORE
+    } else {
+        stand_pat
+    };
+    if value >= beta {
+        return value;
+    }
+    alpha = alpha.max(value);
+
+    let mut moves = MoveGenerator::captures(b).cached_moves;
+    let others = if threatened {
+        let blocks = MoveGenerator::new(b).filter(|m| routes.contains(m.end_index));
+        blocks.collect()
+    } else if !b.attacker_move && depth == QUIESCENCE_DEPTH {
+        // threats are only tried straight after the main search, as chains of them add up fast
+        MoveGenerator::escape_threats(b).cached_moves
+    } else {
+        Vec::new()
+    };
+    for m in others {
+        if !moves.contains(&m) {
+            moves.push(m);
+        }
+    }
+
+    // the generators put their best moves last
+    while let Some(mv) = moves.pop() {
+        b.make_move(mv, &tafl_ai.ttable);
+        let eval = -quiescence(tafl_ai, b, state, -beta, -alpha, -color, depth - 1);
+        b.unmake_move();
+        if state.tm.stopped() {
+            return 0;
+        }
+
+        if eval > value {
+            value = eval;
+            state.pv.update(ply, mv);
+        }
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            break;
+        }
+    }
+    value
+}
+
+// Score of a finished game from the attackers' point of view. Wins found with more depth
+// remaining are closer to the root, so they score higher.
+fn outcome_eval(outcome: Outcome, depth: u8) -> i16 {
+    match outcome {
+        Outcome::AttackersWin(_) => WIN_SCORE + depth as i16,
+        Outcome::DefendersWin(_) => -WIN_SCORE - depth as i16,
+        Outcome::Draw(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use crate::rules::Rules;
+
+    fn engine(limits: SearchLimits) -> TaflAI {
+        TaflAI {
+            limits,
+            multi_pv: 1,
+            ttable: TranspositionTable::with_size_mb(1),
+        }
+    }
+
+    fn is_legal(b: &Board, m: Move) -> bool {
+        MoveGenerator::new(b).any(|legal| legal == m)
+    }
+
+    #[test]
+    fn depth_zero_still_finds_a_move() {
+        let mut ai = engine(SearchLimits::depth(0));
+        let mut b = Board::new(Rules::brandubh());
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert_eq!(rec.depth, 1);
+        assert!(is_legal(&b, rec.best_move));
+    }
+
+    #[test]
+    fn depth_limit_is_searched_in_full() {
+        let mut ai = engine(SearchLimits::depth(3));
+        let mut b = Board::new(Rules::brandubh());
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert_eq!(rec.depth, 3);
+        assert!(is_legal(&b, rec.best_move));
+    }
+
+    #[test]
+    fn node_limit_keeps_the_last_completed_depth() {
+        let mut ai = engine(SearchLimits::depth(3));
+        let mut b = Board::new(Rules::copenhagen());
+        b.rehash(&ai.ttable);
+        let full = ai.find_best_move(&mut b);
+
+        ai.ttable.clear();
+        ai.limits = SearchLimits {
+            nodes: Some(full.nnodes / 2),
+            ..Default::default()
+        };
+        let rec = ai.find_best_move(&mut b);
+        assert!(rec.depth < 3);
+        assert!(rec.nnodes <= full.nnodes / 2 + 1);
+        assert!(is_legal(&b, rec.best_move));
+        assert!(b.history.is_empty());
+        assert_eq!(b.current_hash, ai.ttable.hash_from_board(&b));
+    }
+
+    #[test]
+    fn movetime_aborts_mid_iteration() {
+        let movetime = Duration::from_millis(50);
+        let mut ai = engine(SearchLimits::movetime(movetime));
+        let mut b = Board::new(Rules::copenhagen());
+        b.rehash(&ai.ttable);
+        let now = Instant::now();
+        let rec = ai.find_best_move(&mut b);
+        assert!(now.elapsed() < movetime * 4);
+        assert!(rec.depth >= 1);
+        assert!(is_legal(&b, rec.best_move));
+    }
+
+    #[test]
+    fn tiny_limits_still_give_a_move() {
+        let mut ai = engine(SearchLimits {
+            nodes: Some(1),
+            ..Default::default()
+        });
+        let mut b = Board::new(Rules::tablut());
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert_eq!(rec.depth, 0);
+        assert!(is_legal(&b, rec.best_move));
+    }
+
+    #[test]
+    fn worker_search_reports_progress_until_stopped() {
+        let ai = engine(SearchLimits::default());
+        let mut b = Board::new(Rules::copenhagen());
+        b.rehash(&ai.ttable);
+        let handle = ai.spawn_search(b.clone());
+
+        let mut depths = Vec::new();
+        for info in handle.info.iter() {
+            assert!(is_legal(&b, info.pv[0]));
+            depths.push(info.depth);
+            if info.depth == 2 {
+                handle.stop();
+            }
+        }
+        let (ai, rec) = handle.join();
+        // the third iteration may finish before the stop is noticed, but no more
+        assert!(depths.starts_with(&[1, 2]) && depths.len() <= 3);
+        assert_eq!(Some(&rec.depth), depths.last());
+        assert!(is_legal(&b, rec.best_move));
+        assert_eq!(b.current_hash, ai.ttable.hash_from_board(&b));
+    }
+
+    #[test]
+    fn principal_variation_is_playable() {
+        for rules in [Rules::brandubh(), Rules::tablut(), Rules::copenhagen()] {
+            let mut ai = engine(SearchLimits::depth(4));
+            let mut b = Board::new(rules);
+            b.rehash(&ai.ttable);
+            let rec = ai.find_best_move(&mut b);
+            assert_eq!(rec.pv.first(), Some(&rec.best_move), "{}", rules.name);
+            assert!(rec.pv.len() > 1, "{}", rules.name);
+            for &m in &rec.pv {
+                assert!(is_legal(&b, m), "{}: {} in {:?}", rules.name, m, rec.pv);
+                b.make_move(m, &ai.ttable);
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_moves_are_cut_from_a_line() {
+        let ai = engine(SearchLimits::default());
+        let mut b = Board::new(Rules::brandubh());
+        b.rehash(&ai.ttable);
+        let m = b.parse_move("d6-c6").unwrap();
+        // the second time there's no longer a piece on d6
+        assert_eq!(legal_prefix(&mut b, &[m, m, m], &ai.ttable), [m]);
+        assert!(b.history.is_empty());
+    }
+
+    #[test]
+    fn multi_pv_ranks_distinct_root_moves() {
+        let mut ai = engine(SearchLimits::depth(3));
+        ai.multi_pv = 5;
+        let mut b = Board::new(Rules::brandubh());
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+
+        assert_eq!(rec.lines.len(), 5);
+        assert_eq!(rec.lines[0].m, rec.best_move);
+        assert_eq!(rec.lines[0].evaluation, rec.evaluation);
+        // the attackers are to move, so higher is better
+        assert!(rec
+            .lines
+            .windows(2)
+            .all(|pair| pair[0].evaluation >= pair[1].evaluation));
+        for (i, line) in rec.lines.iter().enumerate() {
+            assert!(rec.lines[..i].iter().all(|other| other.m != line.m));
+            assert_eq!(line.pv[0], line.m);
+            assert_eq!(
+                legal_prefix(&mut b, &line.pv, &ai.ttable).len(),
+                line.pv.len()
+            );
+        }
+    }
+
+    #[test]
+    fn multi_pv_is_limited_by_the_legal_moves() {
+        let mut ai = engine(SearchLimits::depth(2));
+        ai.multi_pv = 20;
+        let mut b = Board::new(Rules::ard_ri());
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        // the defenders start in ard ri, so lower is better
+        assert_eq!(rec.lines.len(), 8);
+        assert!(rec
+            .lines
+            .windows(2)
+            .all(|pair| pair[0].evaluation <= pair[1].evaluation));
+    }
+
+    #[test]
+    fn quiescence_takes_a_hanging_piece() {
+        let mut ai = engine(SearchLimits::default());
+        let mut b = Board::from_notation("brandubh 7/7/7/3K3/7/1O5/2VO3 d").unwrap();
+        b.rehash(&ai.ttable);
+        let stop = AtomicBool::new(false);
+        let mut state = SearchState {
+            tm: TimeManager::new(&ai.limits, &stop),
+            pv: PvTable::new(&b),
+        };
+        let stand_pat = -naive_eval(&b);
+        let value = quiescence(
+            &mut ai,
+            &mut b,
+            &mut state,
+            i16::MIN + 1,
+            i16::MAX - 1,
+            -1,
+            4,
+        );
+        assert!(value > stand_pat);
+        assert_eq!(state.pv.lines[0][0], b.parse_move("b2-b1").unwrap());
+    }
+
+    #[test]
+    fn quiescence_sees_an_unstoppable_escape() {
+        // nothing can be put on a7 to stop the king
+        let mut ai = engine(SearchLimits::depth(1));
+        let mut b = Board::from_notation("brandubh 7/K6/7/2V4/7/7/7 a").unwrap();
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert!(rec.evaluation <= -WIN_SCORE);
+    }
+
+    #[test]
+    fn quiescence_blocks_an_escape() {
+        // the boxed-in king on a3 threatens to reach a1, which only the attacker on d2 can stop
+        let mut ai = engine(SearchLimits::depth(1));
+        let mut b = Board::from_notation("brandubh 7/7/V6/1V5/KV5/3V3/7 a").unwrap();
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert_eq!(rec.best_move, b.parse_move("d2-a2").unwrap());
+        assert!(rec.evaluation > -WIN_SCORE);
+
+        let mut b = Board::from_notation("brandubh 7/7/V6/1V5/KV5/7/7 a").unwrap();
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        assert!(rec.evaluation <= -WIN_SCORE);
+    }
+
+    // Every root move's score from a plain full-window search of its reply, from the
+    // attackers' side.
+    fn full_window_scores(ai: &mut TaflAI, b: &mut Board, depth: u8) -> Vec<(Move, i16)> {
+        let color = if b.attacker_move { 1 } else { -1 };
+        let stop = AtomicBool::new(false);
+        let mut scores = Vec::new();
+        for m in MoveGenerator::new(b).cached_moves {
+            ai.ttable.clear();
+            let mut state = SearchState {
+                tm: TimeManager::new(&ai.limits, &stop),
+                pv: PvTable::new(b),
+            };
+            b.make_move(m, &ai.ttable);
+            let eval = -negamax(
+                ai,
+                b,
+                depth - 1,
+                &mut state,
+                i16::MIN + 1,
+                i16::MAX - 1,
+                -color,
+            );
+            b.unmake_move();
+            scores.push((m, eval * color));
+        }
+        scores
+    }
+
+    // Positions where the score depends on more than the first reply.
+    // The last one fails low at depth 3 and is searched again with a wider window.
+    const WINDOW_POSITIONS: [&str; 3] = [
+        "brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+        "brandubh 4V2/2VV3/6O/VK2O2/6O/2OV3/3V1V1 d",
+        "brandubh 1V2V2/3VO1O/1V5/3KO2/1O5/3VV2/3V1V1 a",
+    ];
+
+    #[test]
+    fn best_root_move_scores_match_a_full_window_search() {
+        for notation in WINDOW_POSITIONS {
+            for depth in 1..=3 {
+                let mut ai = engine(SearchLimits::depth(depth));
+                let mut b = Board::from_notation(notation).unwrap();
+                b.rehash(&ai.ttable);
+                let scores = full_window_scores(&mut ai, &mut b, depth);
+                let best = if b.attacker_move {
+                    scores.iter().map(|&(_, eval)| eval).max()
+                } else {
+                    scores.iter().map(|&(_, eval)| eval).min()
+                };
+                ai.ttable.clear();
+                let rec = ai.find_best_move(&mut b);
+                assert_eq!(Some(rec.evaluation), best, "{} depth {}", notation, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn multi_pv_scores_match_a_full_window_search() {
+        for notation in WINDOW_POSITIONS {
+            for depth in 1..=3 {
+                let mut ai = engine(SearchLimits::depth(depth));
+                ai.multi_pv = 100;
+                let mut b = Board::from_notation(notation).unwrap();
+                b.rehash(&ai.ttable);
+                let scores = full_window_scores(&mut ai, &mut b, depth);
+                ai.ttable.clear();
+                let rec = ai.find_best_move(&mut b);
+                assert_eq!(rec.lines.len(), scores.len());
+                for line in &rec.lines {
+                    let (_, full) = scores.iter().find(|&&(m, _)| m == line.m).unwrap();
+                    assert_eq!(
+                        line.evaluation, *full,
+                        "{} depth {} {}",
+                        notation, depth, line.m
+                    );
+                }
+            }
+        }
+
+        // a line that used to be cut short with the window the wrong way round
+        let mut ai = engine(SearchLimits::depth(2));
+        ai.multi_pv = 100;
+        let mut b = Board::from_notation(WINDOW_POSITIONS[1]).unwrap();
+        b.rehash(&ai.ttable);
+        let rec = ai.find_best_move(&mut b);
+        let m = b.parse_move("b4-b5").unwrap();
+        let line = rec.lines.iter().find(|line| line.m == m).unwrap();
+        assert_eq!(line.evaluation, 800);
+    }
 }
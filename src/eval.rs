@@ -1,4 +1,6 @@
-use crate::board::{inbounds, rc_to_index, Bitboard, Board, BOARD_SIZE, DIRS};
+use crate::bitboard::Bitboard;
+use crate::board::{inbounds, rc_to_index, Board, Outcome, DIRS};
+use crate::rules::Escape;
 
 const KING_VALUE: i16 = 100;
 const DEFENDER_VALUE: i16 = 100;
@@ -15,32 +17,31 @@ fn attackers_next_to_king(b: &Board) -> i16 {
     for &(dr, dc) in DIRS.iter() {
         let new_row = king_row as isize + dr;
         let new_col = king_col as isize + dc;
-        if !inbounds(new_row, new_col) {
+        if !inbounds(new_row, new_col, b.size()) {
             continue;
         }
         let new_index = rc_to_index(new_row as usize, new_col as usize);
-        if b.attacker_board & (1 << new_index) != 0 {
+        if b.attacker_board.contains(new_index) {
             s += 1;
         }
     }
-    return s;
+    s
 }
 
-fn dist_to_corner(b: &Board) -> i16 {
-    let (mut king_row, mut king_col) = b.king_coordinates();
+fn dist_to_escape(b: &Board) -> i16 {
+    let (king_row, king_col) = b.king_coordinates();
+    let last = b.size() - 1;
+    let row_dist = king_row.min(last - king_row);
+    let col_dist = king_col.min(last - king_col);
 
-    if king_row > BOARD_SIZE / 2 {
-        king_row = BOARD_SIZE - king_row;
-    }
-    if king_col > BOARD_SIZE / 2 {
-        king_col = BOARD_SIZE - king_col;
+    match b.rules.escape {
+        Escape::Edge => row_dist.min(col_dist) as i16,
+        Escape::Corner => (row_dist + col_dist) as i16,
     }
-
-    return (king_row + king_col) as i16;
 }
 
 pub fn naive_eval(b: &Board) -> i16 {
-    if b.stalemate {
+    if let Some(Outcome::Draw(_)) = b.result {
         return 0;
     }
 
@@ -48,9 +49,9 @@ pub fn naive_eval(b: &Board) -> i16 {
     let mut defender_score = total_board(b.defender_board);
 
     let attackers_next_to_king = attackers_next_to_king(b);
-    let dist_to_corner = dist_to_corner(b);
-    defender_score -= dist_to_corner;
+    let dist_to_escape = dist_to_escape(b);
+    defender_score -= dist_to_escape;
     attack_score += attackers_next_to_king;
 
-    return attack_score * ATTACKER_VALUE + KING_VALUE - defender_score * DEFENDER_VALUE;
+    attack_score * ATTACKER_VALUE + KING_VALUE - defender_score * DEFENDER_VALUE
 }
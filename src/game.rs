@@ -0,0 +1,184 @@
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+use crate::board::{Board, Move, Outcome};
+use crate::engine::{EngineBenchmark, SearchInfo, TaflAI};
+use crate::record::GameRecord;
+use crate::ui::UI;
+
+/// The sides whose moves the engine chooses. The UI chooses the rest.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineSide {
+    None,
+    Attackers,
+    Defenders,
+    Both,
+}
+
+impl EngineSide {
+    pub fn plays(self, attacker_move: bool) -> bool {
+        match self {
+            EngineSide::None => false,
+            EngineSide::Attackers => attacker_move,
+            EngineSide::Defenders => !attacker_move,
+            EngineSide::Both => true,
+        }
+    }
+}
+
+pub struct Game {
+    pub board: Board,
+    pub record: GameRecord,
+    pub engine_side: EngineSide,
+    /// Whether to show the engine's recommendation before a move from the UI.
+    pub hints: bool,
+}
+
+impl Game {
+    /// A game continuing from the end of a record.
+    pub fn new(record: GameRecord, engine: &TaflAI, engine_side: EngineSide) -> Self {
+        Game {
+            board: record.replay(&engine.ttable),
+            record,
+            engine_side,
+            hints: true,
+        }
+    }
+
+    /// Plays one move chosen by the engine or the UI, depending on the side to move. Returns
+    /// `None` without moving if the game is over or the UI has no more moves.
+    pub fn play_turn<U: UI>(&mut self, engine: &mut TaflAI, ui: &mut U) -> Option<Move> {
+        if self.board.outcome().is_some() {
+            return None;
+        }
+        let engine_to_move = self.engine_side.plays(self.board.attacker_move);
+        let benchmark = if engine_to_move || self.hints {
+            let board = &self.board;
+            let benchmark = search(engine, board, |info| ui.render_info(board, info));
+            ui.render_eval(&self.board, &benchmark);
+            Some(benchmark)
+        } else {
+            None
+        };
+        ui.render_board(&self.board);
+
+        let m = match benchmark {
+            Some(benchmark) if engine_to_move => benchmark.recommendation.best_move,
+            _ => ui.get_move(&self.board)?,
+        };
+        ui.render_move(&self.board, m, engine_to_move);
+        self.record.play(&mut self.board, m, &engine.ttable);
+        Some(m)
+    }
+
+    /// Plays until the game ends or the UI runs out of moves, calling `after_move` after every
+    /// move. Returns the outcome if the game ended.
+    pub fn play_out<U: UI>(
+        &mut self,
+        engine: &mut TaflAI,
+        ui: &mut U,
+        mut after_move: impl FnMut(&Game),
+    ) -> Option<Outcome> {
+        while self.play_turn(engine, ui).is_some() {
+            after_move(self);
+        }
+        let outcome = self.board.outcome();
+        if let Some(outcome) = outcome {
+            ui.render_board(&self.board);
+            ui.game_over(outcome);
+        }
+        outcome
+    }
+}
+
+/// Searches a copy of the board, timing the search and reporting each iteration to
+/// `on_iteration`.
+pub fn search(
+    engine: &mut TaflAI,
+    b: &Board,
+    on_iteration: impl FnMut(&SearchInfo),
+) -> EngineBenchmark {
+    let now = Instant::now();
+    let mut b_for_eval = b.clone();
+    let recommendation = engine.search(&mut b_for_eval, &AtomicBool::new(false), on_iteration);
+    EngineBenchmark {
+        recommendation,
+        elapsed: now.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PieceType;
+    use crate::limits::SearchLimits;
+    use crate::movegen::MoveGenerator;
+    use crate::rules::Rules;
+    use crate::ttable::TranspositionTable;
+
+    // Plays the first legal move a fixed number of times, then runs out.
+    struct ScriptedUI {
+        moves_left: usize,
+    }
+
+    impl UI for ScriptedUI {
+        fn get_move(&mut self, b: &Board) -> Option<Move> {
+            if self.moves_left == 0 {
+                return None;
+            }
+            self.moves_left -= 1;
+            MoveGenerator::new(b).next()
+        }
+        fn render_board(&self, _: &Board) {}
+        fn render_eval(&self, _: &Board, _: &EngineBenchmark) {}
+        fn render_info(&self, _: &Board, _: &SearchInfo) {}
+        fn render_move(&self, _: &Board, _: Move, _: bool) {}
+        fn game_over(&self, _: Outcome) {}
+    }
+
+    fn engine() -> TaflAI {
+        TaflAI {
+            limits: SearchLimits::depth(2),
+            multi_pv: 1,
+            ttable: TranspositionTable::with_size_mb(1),
+        }
+    }
+
+    #[test]
+    fn engine_plays_itself_to_completion() {
+        let mut engine = engine();
+        let rules = Rules {
+            move_limit: Some(60),
+            ..Rules::brandubh()
+        };
+        // replaying the record hashes the board with the engine's table
+        let record = GameRecord::new(&Board::new(rules));
+        let mut game = Game::new(record, &engine, EngineSide::Both);
+        let mut moves = 0;
+        let mut ui = ScriptedUI { moves_left: 0 };
+        let outcome = game.play_out(&mut engine, &mut ui, |_| moves += 1);
+
+        assert!(outcome.is_some());
+        assert_eq!(outcome, game.board.outcome());
+        assert_eq!(game.record.moves.len(), moves);
+        assert!(moves <= 60);
+    }
+
+    #[test]
+    fn engine_answers_moves_from_the_ui() {
+        let mut engine = engine();
+        let record = GameRecord::new(&Board::new(Rules::brandubh()));
+        let mut game = Game::new(record, &engine, EngineSide::Defenders);
+        game.hints = false;
+        let mut ui = ScriptedUI { moves_left: 3 };
+        let outcome = game.play_out(&mut engine, &mut ui, |_| {});
+
+        // the attackers move first, so the game stops when the UI is asked for a fourth move
+        assert_eq!(outcome, None);
+        let pieces: Vec<PieceType> = game.record.moves.iter().map(|rm| rm.m.piece_type).collect();
+        assert_eq!(pieces.len(), 6);
+        for (i, piece) in pieces.into_iter().enumerate() {
+            assert_eq!(piece == PieceType::Attacker, i % 2 == 0);
+        }
+    }
+}
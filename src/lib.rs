@@ -0,0 +1,21 @@
+//! A tafl engine: board representation, rule sets, move generation and search.
+
+pub mod bitboard;
+pub mod board;
+pub mod engine;
+mod eval;
+pub mod game;
+pub mod limits;
+pub mod movegen;
+pub mod notation;
+pub mod perft;
+pub mod record;
+pub mod rules;
+pub mod ttable;
+pub mod ui;
+
+pub use board::{Board, Move, Outcome, PieceType, Reason};
+pub use engine::TaflAI;
+pub use limits::SearchLimits;
+pub use rules::Rules;
+pub use ttable::TranspositionTable;
@@ -0,0 +1,182 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Deepest search the engine will attempt when no depth limit is given.
+pub const MAX_DEPTH: u8 = 64;
+
+// A clock is assumed to cover this many more moves when budgeting time for one of them.
+const MOVES_TO_GO: u32 = 30;
+// Nodes searched between looks at the clock.
+const TIME_CHECK_INTERVAL: usize = 1024;
+
+/// Bounds on a single search. Limits left unset don't apply; with none set the search runs
+/// to `MAX_DEPTH`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    /// Time to spend on this move.
+    pub movetime: Option<Duration>,
+    /// Time left on the clock of the side to move.
+    pub clock: Option<Duration>,
+    /// Time added to the clock after each move. Only used alongside `clock`.
+    pub increment: Duration,
+    pub nodes: Option<usize>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> Self {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    /// Deepest iteration to search. Always at least one, so that a move is found.
+    pub fn max_depth(&self) -> u8 {
+        self.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH)
+    }
+}
+
+/// Decides when a running search has to stop. A search can stop between iterations once the
+/// soft deadline has passed, since the next iteration is unlikely to finish in time, and is
+/// aborted mid-iteration at the hard deadline, the node limit or when asked to stop.
+pub struct TimeManager<'a> {
+    start: Instant,
+    soft_deadline: Option<Duration>,
+    hard_deadline: Option<Duration>,
+    node_limit: Option<usize>,
+    pub nodes: usize,
+    stop: &'a AtomicBool,
+    stopped: bool,
+}
+
+impl<'a> TimeManager<'a> {
+    pub fn new(limits: &SearchLimits, stop: &'a AtomicBool) -> Self {
+        let mut soft_deadline = limits.movetime;
+        let mut hard_deadline = limits.movetime;
+        if let Some(clock) = limits.clock {
+            let budget = clock / MOVES_TO_GO + limits.increment * 3 / 4;
+            // never risk more than half of what's left, even with a large increment
+            let hard = (budget * 2).min(clock / 2);
+            soft_deadline = Some(soft_deadline.map_or(budget / 2, |s| s.min(budget / 2)));
+            hard_deadline = Some(hard_deadline.map_or(hard, |h| h.min(hard)));
+        }
+        TimeManager {
+            start: Instant::now(),
+            soft_deadline,
+            hard_deadline,
+            node_limit: limits.nodes,
+            nodes: 0,
+            stop,
+            stopped: false,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Whether another iteration is worth starting.
+    pub fn can_deepen(&self) -> bool {
+        !self.stopped
+            && !self.stop.load(Ordering::Relaxed)
+            && self
+                .soft_deadline
+                .is_none_or(|deadline| self.elapsed() < deadline)
+            && self.node_limit.is_none_or(|limit| self.nodes < limit)
+    }
+
+    /// Whether the search has to be abandoned now. Once true, it stays true.
+    pub fn should_stop(&mut self) -> bool {
+        if !self.stopped {
+            self.stopped = self.node_limit.is_some_and(|limit| self.nodes >= limit)
+                || (self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+                    && (self.stop.load(Ordering::Relaxed)
+                        || self
+                            .hard_deadline
+                            .is_some_and(|deadline| self.elapsed() >= deadline)));
+        }
+        self.stopped
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_depth_is_at_least_one() {
+        assert_eq!(SearchLimits::depth(0).max_depth(), 1);
+        assert_eq!(SearchLimits::depth(5).max_depth(), 5);
+        assert_eq!(SearchLimits::depth(200).max_depth(), MAX_DEPTH);
+        assert_eq!(SearchLimits::default().max_depth(), MAX_DEPTH);
+    }
+
+    #[test]
+    fn clock_budget_stays_within_the_clock() {
+        let stop = AtomicBool::new(false);
+        let tm = TimeManager::new(
+            &SearchLimits {
+                clock: Some(Duration::from_secs(60)),
+                increment: Duration::from_secs(2),
+                ..Default::default()
+            },
+            &stop,
+        );
+        // 60s / 30 + 3/4 of the 2s increment
+        assert_eq!(tm.soft_deadline, Some(Duration::from_millis(1750)));
+        assert_eq!(tm.hard_deadline, Some(Duration::from_millis(7000)));
+
+        let tm = TimeManager::new(
+            &SearchLimits {
+                clock: Some(Duration::from_secs(1)),
+                increment: Duration::from_secs(10),
+                movetime: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            &stop,
+        );
+        assert_eq!(tm.soft_deadline, Some(Duration::from_millis(100)));
+        assert_eq!(tm.hard_deadline, Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn node_limit_stops_the_search() {
+        let stop = AtomicBool::new(false);
+        let mut tm = TimeManager::new(
+            &SearchLimits {
+                nodes: Some(10),
+                ..Default::default()
+            },
+            &stop,
+        );
+        tm.nodes = 9;
+        assert!(!tm.should_stop());
+        assert!(tm.can_deepen());
+        tm.nodes = 10;
+        assert!(tm.should_stop());
+        assert!(!tm.can_deepen());
+    }
+
+    #[test]
+    fn stop_flag_stops_the_search() {
+        let stop = AtomicBool::new(false);
+        let mut tm = TimeManager::new(&SearchLimits::default(), &stop);
+        tm.nodes = TIME_CHECK_INTERVAL;
+        assert!(!tm.should_stop());
+        stop.store(true, Ordering::Relaxed);
+        assert!(!tm.can_deepen());
+        assert!(tm.should_stop());
+    }
+}
@@ -1,13 +1,39 @@
-use crate::board::{
-    inbounds, index_to_rc, rc_to_index, valid_capture, Board, Move, PieceType, BOARD_SIZE, DIRS,
-    EMPTY_BOARD,
-};
+use crate::bitboard::{Bitboard, EMPTY_BOARD, MAX_BOARD_SIZE, NUM_SQUARES};
+use crate::board::{index_to_rc, rc_to_index, valid_capture, Board, Move, PieceType, DIRS};
+use crate::rules::Movement;
 
 const KING_ESCAPE_SCORE: i16 = 5000;
 const MOVE_TO_KING_SCORE: i16 = 1000;
 const CAPTURE_SCORE: i16 = 1000;
 const NORMAL_MOVE_SCORE: i16 = 0;
 
+// RAYS[d][i] holds every square reachable from square i by walking in direction DIRS[d]
+// until the edge of the largest board, ignoring any pieces in the way. Smaller boards mask
+// the rays with their own squares.
+static RAYS: [[Bitboard; NUM_SQUARES]; 4] = make_rays();
+
+const fn make_rays() -> [[Bitboard; NUM_SQUARES]; 4] {
+    let mut rays = [[EMPTY_BOARD; NUM_SQUARES]; 4];
+    let mut d = 0;
+    while d < 4 {
+        let (dr, dc) = DIRS[d];
+        let mut index = 0;
+        while index < NUM_SQUARES {
+            let (row, col) = index_to_rc(index);
+            let mut r = row as isize + dr;
+            let mut c = col as isize + dc;
+            while r >= 0 && r < MAX_BOARD_SIZE as isize && c >= 0 && c < MAX_BOARD_SIZE as isize {
+                rays[d][index] = rays[d][index].with(rc_to_index(r as usize, c as usize));
+                r += dr;
+                c += dc;
+            }
+            index += 1;
+        }
+        d += 1;
+    }
+    rays
+}
+
 #[derive(Debug)]
 struct ScoredMove {
     mv: Move,
@@ -40,13 +66,39 @@ pub struct MoveGenerator {
 
 impl MoveGenerator {
     pub fn new(board: &Board) -> Self {
+        Self::generate(board, |_, _| true)
+    }
+
+    /// Moves that capture a piece or the king, best first.
+    pub fn captures(board: &Board) -> Self {
+        Self::generate(board, is_capture)
+    }
+
+    /// King moves that escape, or that leave the king able to escape on the defenders' next
+    /// move. There are none when the attackers are to move.
+    pub fn escape_threats(board: &Board) -> Self {
+        let mut moves = Vec::new();
+        if !board.attacker_move {
+            let occupied = board.attacker_board | board.defender_board | board.king_board;
+            gen_piece_moves(
+                board,
+                board.king_index(),
+                occupied,
+                EMPTY_BOARD,
+                PieceType::King,
+                &mut moves,
+                threatens_escape,
+            );
+        }
+        Self::sorted(moves)
+    }
+
+    fn generate(board: &Board, keep: impl Fn(&Board, &Move) -> bool + Copy) -> Self {
         let mut all_moves = Vec::with_capacity(32);
         let occupied = board.attacker_board | board.defender_board | board.king_board;
 
         if board.attacker_move {
-            let mut current_attackers = board.attacker_board;
-            while current_attackers != 0 {
-                let start_index = current_attackers.trailing_zeros() as usize;
+            for start_index in board.attacker_board {
                 gen_piece_moves(
                     board,
                     start_index,
@@ -54,13 +106,11 @@ impl MoveGenerator {
                     board.offlimits_board,
                     PieceType::Attacker,
                     &mut all_moves,
+                    keep,
                 );
-                current_attackers &= !(1 << start_index);
             }
         } else {
-            let mut current_defenders = board.defender_board;
-            while current_defenders != 0 {
-                let start_index = current_defenders.trailing_zeros() as usize;
+            for start_index in board.defender_board {
                 gen_piece_moves(
                     board,
                     start_index,
@@ -68,8 +118,8 @@ impl MoveGenerator {
                     board.offlimits_board,
                     PieceType::Defender,
                     &mut all_moves,
+                    keep,
                 );
-                current_defenders &= !(1 << start_index);
             }
             gen_piece_moves(
                 board,
@@ -78,12 +128,16 @@ impl MoveGenerator {
                 EMPTY_BOARD,
                 PieceType::King,
                 &mut all_moves,
+                keep,
             );
         }
+        Self::sorted(all_moves)
+    }
 
-        all_moves.sort_unstable();
+    fn sorted(mut moves: Vec<ScoredMove>) -> Self {
+        moves.sort_unstable();
         Self {
-            cached_moves: all_moves.into_iter().map(|sm| sm.mv).collect(),
+            cached_moves: moves.into_iter().map(|sm| sm.mv).collect(),
         }
     }
 }
@@ -96,76 +150,176 @@ impl Iterator for MoveGenerator {
     }
 }
 
+/// Whether the side to move has at least one legal move.
+pub fn has_legal_moves(board: &Board) -> bool {
+    let occupied = board.attacker_board | board.defender_board | board.king_board;
+    if board.attacker_move {
+        board
+            .attacker_board
+            .into_iter()
+            .any(|i| !targets(board, i, occupied, board.offlimits_board).is_empty())
+    } else {
+        board
+            .defender_board
+            .into_iter()
+            .any(|i| !targets(board, i, occupied, board.offlimits_board).is_empty())
+            || !targets(board, board.king_index(), occupied, EMPTY_BOARD).is_empty()
+    }
+}
+
+/// Squares the king could escape through on the defenders' next move: every open line from
+/// the king to an escape square, the escape square included. Moving onto one blocks a route.
+pub fn escape_routes(board: &Board) -> Bitboard {
+    let king_index = board.king_index();
+    let occupied = board.attacker_board | board.defender_board | board.king_board;
+    let escapes = targets(board, king_index, occupied, EMPTY_BOARD) & board.escape_board();
+    let mut routes = EMPTY_BOARD;
+    for escape_index in escapes {
+        for ray in RAYS.iter() {
+            if ray[king_index].contains(escape_index) {
+                routes |= ray[king_index] & !ray[escape_index];
+            }
+        }
+    }
+    routes
+}
+
+// Squares a piece on start_index may land on, given the occupied squares. Off-limits
+// squares can be passed through while sliding but never landed on.
+fn targets(board: &Board, start_index: usize, occupied: Bitboard, offlimits: Bitboard) -> Bitboard {
+    let mut targets = EMPTY_BOARD;
+    for (d, &(dr, dc)) in DIRS.iter().enumerate() {
+        let ray = RAYS[d][start_index] & board.geometry.squares;
+        if ray.is_empty() {
+            continue;
+        }
+        let reachable = match board.rules.movement {
+            Movement::Step => ray & !RAYS[d][nearest_square(ray, dr, dc)],
+            Movement::Slide => {
+                let blockers = ray & occupied;
+                if blockers.is_empty() {
+                    ray
+                } else {
+                    ray & !RAYS[d][nearest_square(blockers, dr, dc)]
+                }
+            }
+        };
+        targets |= reachable;
+    }
+    targets & !occupied & !offlimits
+}
+
+// The square of bb closest to the origin of a ray travelling in direction (dr, dc).
+#[inline(always)]
+fn nearest_square(bb: Bitboard, dr: isize, dc: isize) -> usize {
+    if dr + dc > 0 {
+        bb.trailing_zeros() as usize
+    } else {
+        bb.last_square()
+    }
+}
+
 fn gen_piece_moves(
     board: &Board,
     start_index: usize,
-    occupied: u64,
-    offlimits: u64,
+    occupied: Bitboard,
+    offlimits: Bitboard,
     piece_type: PieceType,
     moves: &mut Vec<ScoredMove>,
+    keep: impl Fn(&Board, &Move) -> bool,
 ) {
-    let (start_row, start_col) = index_to_rc(start_index);
-    for &(dr, dc) in DIRS.iter() {
-        let end_row = start_row as isize + dr;
-        let end_col = start_col as isize + dc;
-
-        if !inbounds(end_row, end_col) {
+    for end_index in targets(board, start_index, occupied, offlimits) {
+        let mv = Move {
+            start_index,
+            end_index,
+            piece_type,
+        };
+        if !keep(board, &mv) {
             continue;
         }
-        let end_index = rc_to_index(end_row as usize, end_col as usize);
-        // A move is valid if the target square is not occupied and not off-limits for movement
-        if (occupied | offlimits) & (1u64 << end_index) == 0 {
-            let mv = Move {
-                start_index,
-                end_index,
-                piece_type,
-            };
-            let sm = ScoredMove {
-                mv,
-                score: score_move(board, &mv),
-            };
-            moves.push(sm);
-        }
+        moves.push(ScoredMove {
+            mv,
+            score: score_move(board, &mv),
+        });
+    }
+}
+
+// Whether m captures a piece or the king, judged from the position before it's made. The square
+// the piece leaves can never be the far side of one of its captures, since the captured piece
+// would have been in its way.
+fn is_capture(board: &Board, m: &Move) -> bool {
+    let capturee_board = match m.piece_type {
+        PieceType::Attacker if board.king_captured(m.end_index) => return true,
+        PieceType::Attacker => board.defender_board,
+        PieceType::King if !board.rules.armed_king => return false,
+        _ => board.attacker_board,
+    };
+    let capturer_board = board.hammer_board(m.piece_type);
+    let (end_row, end_col) = index_to_rc(m.end_index);
+    let captures = DIRS.iter().any(|dir| {
+        valid_capture(
+            board.size(),
+            capturer_board,
+            capturee_board,
+            (end_row as isize, end_col as isize),
+            (end_row as isize + dir.0, end_col as isize + dir.1),
+        )
+    });
+    if captures {
+        return true;
+    }
+
+    // a shieldwall needs the mover in place, so only try one next to an enemy on the edge
+    let edges = board.geometry.edges;
+    board.rules.shieldwall
+        && edges.contains(m.end_index)
+        && !(Bitboard::square(m.end_index).neighbours() & capturee_board & edges).is_empty()
+        && !board.captures_of(*m).is_empty()
+}
+
+// Whether the king, after m, stands on an escape square or could reach one next move.
+fn threatens_escape(board: &Board, m: &Move) -> bool {
+    let escapes = board.escape_board();
+    if escapes.contains(m.end_index) {
+        return true;
+    }
+    let mut occupied = (board.attacker_board | board.defender_board | board.king_board)
+        ^ Bitboard::square(m.start_index)
+        ^ Bitboard::square(m.end_index);
+    // an armed king can clear its own way
+    if is_capture(board, m) {
+        occupied ^= board.captures_of(*m);
     }
+    !(targets(board, m.end_index, occupied, EMPTY_BOARD) & escapes).is_empty()
 }
 
 fn score_move(board: &Board, m: &Move) -> i16 {
     let mut score = NORMAL_MOVE_SCORE;
-    let (end_row, end_col) = index_to_rc(board.king_index());
+    let (end_row, end_col) = index_to_rc(m.end_index);
 
-    if m.piece_type == PieceType::King {
-        if (end_row == 0 || end_row == BOARD_SIZE - 1)
-            && (end_col == 0 || end_col == BOARD_SIZE - 1)
-        {
-            return KING_ESCAPE_SCORE;
-        }
+    if m.piece_type == PieceType::King && board.escape_board().contains(m.end_index) {
+        return KING_ESCAPE_SCORE;
     }
 
     if m.piece_type == PieceType::Attacker {
         let (king_row, king_col) = board.king_coordinates();
-        let (end_row, end_col) = index_to_rc(m.end_index);
         if king_row.abs_diff(end_row) + king_col.abs_diff(end_col) < 2 {
             score += MOVE_TO_KING_SCORE;
         }
     }
 
-    let capturer_board: u64;
-    let capturee_board: u64;
-    match m.piece_type {
-        PieceType::Attacker => {
-            capturer_board = board.attacker_board;
-            capturee_board = board.defender_board;
-        }
-        _ => {
-            capturer_board = board.defender_board | board.king_board;
-            capturee_board = board.attacker_board;
-        }
-    }
+    let capturer_board = board.hammer_board(m.piece_type);
+    let capturee_board = match m.piece_type {
+        PieceType::Attacker => board.defender_board,
+        PieceType::King if !board.rules.armed_king => return score,
+        _ => board.attacker_board,
+    };
 
     for dir in DIRS {
         let capturee_row = end_row as isize + dir.0;
         let capturee_col = end_col as isize + dir.1;
         if valid_capture(
+            board.size(),
             capturer_board,
             capturee_board,
             (end_row as isize, end_col as isize),
@@ -177,3 +331,57 @@ fn score_move(board: &Board, m: &Move) -> i16 {
 
     score
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{parse_square, square_name};
+
+    // Squares the piece on `from` can move to, in square order.
+    fn destinations(notation: &str, from: &str) -> Vec<String> {
+        let b = Board::from_notation(notation).unwrap();
+        let start = parse_square(from, b.size()).unwrap();
+        let mut ends: Vec<usize> = MoveGenerator::new(&b)
+            .filter(|m| m.start_index == start)
+            .map(|m| m.end_index)
+            .collect();
+        ends.sort_unstable();
+        ends.into_iter().map(square_name).collect()
+    }
+
+    #[test]
+    fn step_moves_one_square() {
+        // ard ri moves one square; the empty throne on d4 can't be stepped onto
+        assert_eq!(
+            destinations("ard-ri 7/7/7/2V2K1/7/7/7 a", "c4"),
+            ["c3", "b4", "c5"]
+        );
+        assert_eq!(
+            destinations("ard-ri 7/7/7/2V2K1/7/7/7 d", "f4"),
+            ["f3", "e4", "g4", "f5"]
+        );
+    }
+
+    #[test]
+    fn slide_moves_until_blocked() {
+        assert_eq!(
+            destinations("brandubh 7/7/7/2V2K1/7/7/7 a", "c4"),
+            ["c1", "c2", "c3", "a4", "b4", "e4", "c5", "c6", "c7"]
+        );
+    }
+
+    #[test]
+    fn slide_passes_through_restricted_squares() {
+        // the attacker crosses the empty throne without stopping on it, and can't land on
+        // a corner
+        assert_eq!(
+            destinations("brandubh 7/7/7/V5K/7/7/7 a", "a4"),
+            ["a2", "a3", "b4", "c4", "e4", "f4", "a5", "a6"]
+        );
+        // the king may stop on either
+        assert_eq!(
+            destinations("brandubh 7/7/7/V5K/7/7/7 d", "g4"),
+            ["g1", "g2", "g3", "b4", "c4", "d4", "e4", "f4", "g5", "g6", "g7"]
+        );
+    }
+}
@@ -0,0 +1,711 @@
+use std::fmt;
+
+use crate::bitboard::{Bitboard, EMPTY_BOARD};
+use crate::board::{index_to_rc, rc_to_index, Board, Move, PieceType};
+use crate::movegen::MoveGenerator;
+use crate::rules::{Movement, Rules};
+
+// A position is written as `<rule set> <layout> <side to move>`, for example
+// `brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a`. The layout lists the ranks from the top of the
+// board down, separated by `/`, with `V` for attackers, `O` for defenders, `K` for the king
+// and a number for a run of empty squares. The side to move is `a` or `d`.
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NotationError {
+    MissingField(&'static str),
+    TrailingInput(String),
+    UnknownRules(String),
+    RankCount {
+        expected: usize,
+        found: usize,
+    },
+    RankLength {
+        rank: usize,
+        expected: usize,
+        found: usize,
+    },
+    UnknownPiece(char),
+    RestrictedSquare {
+        row: usize,
+        col: usize,
+    },
+    KingCount(usize),
+    UnknownSide(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotationError::MissingField(field) => write!(f, "missing {}", field),
+            NotationError::TrailingInput(s) => write!(f, "unexpected input after position: {}", s),
+            NotationError::UnknownRules(name) => write!(f, "unknown rule set: {}", name),
+            NotationError::RankCount { expected, found } => {
+                write!(f, "expected {} ranks, found {}", expected, found)
+            }
+            NotationError::RankLength {
+                rank,
+                expected,
+                found,
+            } => write!(
+                f,
+                "rank {} has {} squares, expected {}",
+                rank, found, expected
+            ),
+            NotationError::UnknownPiece(c) => write!(f, "unknown piece: {}", c),
+            NotationError::RestrictedSquare { row, col } => write!(
+                f,
+                "only the king may stand on {}",
+                square_name(rc_to_index(*row, *col))
+            ),
+            NotationError::KingCount(n) => write!(f, "expected one king, found {}", n),
+            NotationError::UnknownSide(s) => write!(f, "unknown side to move: {}", s),
+        }
+    }
+}
+
+impl Board {
+    /// Reads a position written by `to_notation`. The hash is left at zero, as in `Board::new`.
+    pub fn from_notation(s: &str) -> Result<Board, NotationError> {
+        let s = s.trim();
+        let (name, position) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        if name.is_empty() {
+            return Err(NotationError::MissingField("rule set"));
+        }
+        let rules =
+            Rules::from_name(name).ok_or_else(|| NotationError::UnknownRules(name.to_string()))?;
+        Board::with_position(rules, position)
+    }
+
+    /// Reads the layout and side to move of a position under the given rules.
+    pub fn with_position(rules: Rules, position: &str) -> Result<Board, NotationError> {
+        let mut fields = position.split_whitespace();
+        let layout = fields.next().ok_or(NotationError::MissingField("layout"))?;
+        let side = fields
+            .next()
+            .ok_or(NotationError::MissingField("side to move"))?;
+        let rest: Vec<&str> = fields.collect();
+        if !rest.is_empty() {
+            return Err(NotationError::TrailingInput(rest.join(" ")));
+        }
+
+        let mut board = Board::empty(rules);
+        let size = rules.board_size;
+        let ranks: Vec<&str> = layout.split('/').collect();
+        if ranks.len() != size {
+            return Err(NotationError::RankCount {
+                expected: size,
+                found: ranks.len(),
+            });
+        }
+
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = size - 1 - i;
+            let mut col: usize = 0;
+            let mut empty_run: usize = 0;
+            // lengths saturate rather than overflow; anything that large is rejected below
+            for c in rank.chars() {
+                if let Some(d) = c.to_digit(10) {
+                    empty_run = empty_run.saturating_mul(10).saturating_add(d as usize);
+                    continue;
+                }
+                col = col.saturating_add(empty_run);
+                empty_run = 0;
+
+                let target = match c {
+                    'V' => &mut board.attacker_board,
+                    'O' => &mut board.defender_board,
+                    'K' => &mut board.king_board,
+                    _ => return Err(NotationError::UnknownPiece(c)),
+                };
+                if col < size {
+                    let index = rc_to_index(row, col);
+                    if c != 'K' && board.offlimits_board.contains(index) {
+                        return Err(NotationError::RestrictedSquare { row, col });
+                    }
+                    *target |= Bitboard::square(index);
+                }
+                col = col.saturating_add(1);
+            }
+            col = col.saturating_add(empty_run);
+            if col != size {
+                return Err(NotationError::RankLength {
+                    rank: row + 1,
+                    expected: size,
+                    found: col,
+                });
+            }
+        }
+
+        let kings = board.king_board.count_ones() as usize;
+        if kings != 1 {
+            return Err(NotationError::KingCount(kings));
+        }
+
+        board.attacker_move = match side {
+            "a" => true,
+            "d" => false,
+            _ => return Err(NotationError::UnknownSide(side.to_string())),
+        };
+        Ok(board)
+    }
+
+    pub fn to_notation(&self) -> String {
+        let side = if self.attacker_move { 'a' } else { 'd' };
+        format!("{} {} {}", self.rules.name, self.layout(), side)
+    }
+
+    /// The piece layout part of the notation.
+    pub fn layout(&self) -> String {
+        let size = self.size();
+        let mut ranks = Vec::with_capacity(size);
+        for row in (0..size).rev() {
+            let mut rank = String::new();
+            let mut empty_run = 0;
+            for col in 0..size {
+                let index = rc_to_index(row, col);
+                let c = if self.attacker_board.contains(index) {
+                    'V'
+                } else if self.defender_board.contains(index) {
+                    'O'
+                } else if self.king_board.contains(index) {
+                    'K'
+                } else {
+                    empty_run += 1;
+                    continue;
+                };
+                if empty_run > 0 {
+                    rank.push_str(&empty_run.to_string());
+                    empty_run = 0;
+                }
+                rank.push(c);
+            }
+            if empty_run > 0 {
+                rank.push_str(&empty_run.to_string());
+            }
+            ranks.push(rank);
+        }
+        ranks.join("/")
+    }
+}
+
+/// Name of a square, like `a1` for the bottom left corner.
+pub fn square_name(index: usize) -> String {
+    let (row, col) = index_to_rc(index);
+    format!("{}{}", (b'a' + col as u8) as char, row + 1)
+}
+
+/// Index of a named square, if it is on a board of the given size.
+pub fn parse_square(s: &str, size: usize) -> Option<usize> {
+    let file = s.chars().next()?;
+    let rank = &s[file.len_utf8()..];
+    if !file.is_ascii_lowercase()
+        || rank.is_empty()
+        || rank.starts_with('0')
+        || !rank.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let col = (file as u8 - b'a') as usize;
+    let row = rank.parse::<usize>().ok()? - 1;
+    if col >= size || row >= size {
+        return None;
+    }
+    Some(rc_to_index(row, col))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// The input doesn't look like a move.
+    Syntax(String),
+    /// A square that isn't on the board.
+    BadSquare(String),
+    EmptySquare(String),
+    NotYourTurn(String),
+    /// A `K` prefix on a piece that isn't the king.
+    NotTheKing(String),
+    /// A short move that none of the side's pieces can make.
+    NoPieceCanReach(String),
+    /// A short move that more than one piece can make.
+    Ambiguous(String),
+    /// Captures that don't match the ones the move makes.
+    WrongCaptures(String),
+    Illegal(&'static str),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::Syntax(s) => write!(f, "Unable to parse move \"{}\"", s),
+            MoveError::BadSquare(s) => write!(f, "{} is not a square on this board", s),
+            MoveError::EmptySquare(s) => write!(f, "There is no piece on {}", s),
+            MoveError::NotYourTurn(s) => write!(f, "The piece on {} belongs to the other side", s),
+            MoveError::NotTheKing(s) => write!(f, "The piece on {} is not the king", s),
+            MoveError::NoPieceCanReach(s) => write!(f, "No piece can play {}", s),
+            MoveError::Ambiguous(s) => write!(f, "More than one piece can play {}", s),
+            MoveError::WrongCaptures(s) => write!(f, "{} does not capture those pieces", s),
+            MoveError::Illegal(reason) => write!(f, "Illegal move: {}", reason),
+        }
+    }
+}
+
+// Moves are written in long notation as `d1-d3`, or in short notation as just the destination
+// with a file, rank or whole origin square in front when more than one piece could move there,
+// like `d3`, `ad3`, `1d3` or `a1d3`. Short king moves start with `K`. Either form may end with
+// `x` and the captured squares separated by `/`, like `d1-d3xd4/c3`.
+
+impl Board {
+    pub fn long_move_notation(&self, m: Move) -> String {
+        format!("{}{}", m, self.capture_suffix(m))
+    }
+
+    pub fn short_move_notation(&self, m: Move) -> String {
+        let is_king = m.piece_type == PieceType::King;
+        let (row, col) = index_to_rc(m.start_index);
+        let others: Vec<Move> = MoveGenerator::new(self)
+            .filter(|o| {
+                o.end_index == m.end_index
+                    && o.start_index != m.start_index
+                    && (o.piece_type == PieceType::King) == is_king
+            })
+            .collect();
+        let file = square_name(m.start_index)[..1].to_string();
+        let rank = (row + 1).to_string();
+        let origin = if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|o| index_to_rc(o.start_index).1 != col) {
+            file
+        } else if others.iter().all(|o| index_to_rc(o.start_index).0 != row) {
+            rank
+        } else {
+            file + &rank
+        };
+        format!(
+            "{}{}{}{}",
+            if is_king { "K" } else { "" },
+            origin,
+            square_name(m.end_index),
+            self.capture_suffix(m)
+        )
+    }
+
+    fn capture_suffix(&self, m: Move) -> String {
+        let captured: Vec<String> = self.captures_of(m).into_iter().map(square_name).collect();
+        if captured.is_empty() {
+            String::new()
+        } else {
+            format!("x{}", captured.join("/"))
+        }
+    }
+
+    /// Reads a legal move for the side to move in long or short notation. The hyphen of long
+    /// notation may be left out, and a trailing `x` without squares only says that the move
+    /// captures something.
+    pub fn parse_move(&self, s: &str) -> Result<Move, MoveError> {
+        let syntax = || MoveError::Syntax(s.to_string());
+        let (squares, captures) = match s.split_once('x') {
+            Some((squares, captures)) => (squares, Some(captures)),
+            None => (s, None),
+        };
+        let (king, squares) = match squares.strip_prefix('K') {
+            Some(rest) => (true, rest),
+            None => (false, squares),
+        };
+
+        // the destination is the last file letter and the digits after it
+        let dest_at = squares
+            .rfind(|c: char| c.is_ascii_alphabetic())
+            .ok_or_else(syntax)?;
+        let (origin, to) = squares.split_at(dest_at);
+        let origin = origin.strip_suffix('-').unwrap_or(origin);
+        if !is_square_like(to) {
+            return Err(syntax());
+        }
+        let end_index =
+            parse_square(to, self.size()).ok_or_else(|| MoveError::BadSquare(to.to_string()))?;
+
+        let m = if is_square_like(origin) {
+            let start_index = parse_square(origin, self.size())
+                .ok_or_else(|| MoveError::BadSquare(origin.to_string()))?;
+            self.move_from(origin, start_index, end_index, king)?
+        } else {
+            self.short_move(s, origin, end_index, king)?
+        };
+
+        if captures == Some("") {
+            if self.captures_of(m).is_empty() {
+                return Err(MoveError::WrongCaptures(m.to_string()));
+            }
+        } else if let Some(captures) = captures {
+            let mut listed = EMPTY_BOARD;
+            for square in captures.split('/') {
+                if !is_square_like(square) {
+                    return Err(syntax());
+                }
+                listed |= Bitboard::square(
+                    parse_square(square, self.size())
+                        .ok_or_else(|| MoveError::BadSquare(square.to_string()))?,
+                );
+            }
+            if listed != self.captures_of(m) {
+                return Err(MoveError::WrongCaptures(m.to_string()));
+            }
+        }
+        Ok(m)
+    }
+
+    // A move with an explicit origin square.
+    fn move_from(
+        &self,
+        origin: &str,
+        start_index: usize,
+        end_index: usize,
+        king: bool,
+    ) -> Result<Move, MoveError> {
+        let piece_type = if self.attacker_board.contains(start_index) {
+            PieceType::Attacker
+        } else if self.king_board.contains(start_index) {
+            PieceType::King
+        } else if self.defender_board.contains(start_index) {
+            PieceType::Defender
+        } else {
+            return Err(MoveError::EmptySquare(origin.to_string()));
+        };
+        if (piece_type == PieceType::Attacker) != self.attacker_move {
+            return Err(MoveError::NotYourTurn(origin.to_string()));
+        }
+        if king && piece_type != PieceType::King {
+            return Err(MoveError::NotTheKing(origin.to_string()));
+        }
+
+        let m = Move {
+            start_index,
+            end_index,
+            piece_type,
+        };
+        if self.outcome().is_some() {
+            return Err(MoveError::Illegal("the game is over"));
+        }
+        if MoveGenerator::new(self).any(|legal| legal == m) {
+            Ok(m)
+        } else {
+            Err(MoveError::Illegal(self.illegal_reason(m)))
+        }
+    }
+
+    // A move given by its destination and optionally the file or rank it starts from.
+    fn short_move(
+        &self,
+        s: &str,
+        origin: &str,
+        end_index: usize,
+        king: bool,
+    ) -> Result<Move, MoveError> {
+        let (file, rank) = match origin.chars().next() {
+            None => (None, None),
+            Some(c) if c.is_ascii_lowercase() && origin.len() == 1 => {
+                (Some((c as u8 - b'a') as usize), None)
+            }
+            Some(_) => match origin.parse::<usize>() {
+                Ok(rank) if rank > 0 && !origin.starts_with(['0', '+']) => (None, Some(rank - 1)),
+                _ => return Err(MoveError::Syntax(s.to_string())),
+            },
+        };
+        let candidates: Vec<Move> = MoveGenerator::new(self)
+            .filter(|m| {
+                let (row, col) = index_to_rc(m.start_index);
+                m.end_index == end_index
+                    && (m.piece_type == PieceType::King) == king
+                    && file.is_none_or(|f| f == col)
+                    && rank.is_none_or(|r| r == row)
+            })
+            .collect();
+        match candidates[..] {
+            [m] => Ok(m),
+            [] => Err(MoveError::NoPieceCanReach(s.to_string())),
+            _ => Err(MoveError::Ambiguous(s.to_string())),
+        }
+    }
+
+    // Why a move that the move generator doesn't produce is illegal.
+    fn illegal_reason(&self, m: Move) -> &'static str {
+        let (start_row, start_col) = index_to_rc(m.start_index);
+        let (end_row, end_col) = index_to_rc(m.end_index);
+        let distance = start_row.abs_diff(end_row) + start_col.abs_diff(end_col);
+        if start_row != end_row && start_col != end_col || distance == 0 {
+            return "pieces move along a rank or file";
+        }
+        if self.rules.movement == Movement::Step && distance > 1 {
+            return "pieces move one square at a time";
+        }
+        let occupied = self.attacker_board | self.defender_board | self.king_board;
+        if occupied.contains(m.end_index) {
+            return "the destination is occupied";
+        }
+        if m.piece_type != PieceType::King && self.offlimits_board.contains(m.end_index) {
+            return "only the king may stop on a restricted square";
+        }
+        "the path is blocked"
+    }
+}
+
+// A file letter followed by digits, whether or not it names a square on the board.
+fn is_square_like(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase())
+        && s.len() > 1
+        && s[1..].bytes().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::STARTING_BOARD;
+    use crate::ttable::TranspositionTable;
+
+    fn assert_same_position(a: &Board, b: &Board) {
+        assert_eq!(a.rules, b.rules);
+        assert_eq!(a.attacker_board, b.attacker_board);
+        assert_eq!(a.defender_board, b.defender_board);
+        assert_eq!(a.king_board, b.king_board);
+        assert_eq!(a.offlimits_board, b.offlimits_board);
+        assert_eq!(a.attacker_move, b.attacker_move);
+    }
+
+    #[test]
+    fn starting_positions_round_trip() {
+        for name in ["ard-ri", "brandubh", "fetlar", "copenhagen", "tablut"] {
+            let board = Board::new(Rules::from_name(name).unwrap());
+            let notation = board.to_notation();
+            assert!(notation.starts_with(name));
+            assert_same_position(&board, &Board::from_notation(&notation).unwrap());
+        }
+    }
+
+    #[test]
+    fn starting_board_notation() {
+        assert_eq!(
+            STARTING_BOARD.to_notation(),
+            "ard-ri 2VVV2/3V3/V1OOO1V/VVOKOVV/V1OOO1V/3V3/2VVV2 d"
+        );
+    }
+
+    #[test]
+    fn position_after_moves_round_trips() {
+        let tt = TranspositionTable::new();
+        let mut board = Board::new(Rules::copenhagen());
+        let moves = [
+            (PieceType::Attacker, (10, 3), (8, 3)),
+            (PieceType::Defender, (5, 3), (5, 2)),
+        ];
+        for (piece_type, from, to) in moves {
+            let m = Move {
+                start_index: rc_to_index(from.0, from.1),
+                end_index: rc_to_index(to.0, to.1),
+                piece_type,
+            };
+            board.make_move(m, &tt);
+        }
+        let notation = board.to_notation();
+        assert_eq!(
+            notation,
+            "copenhagen 4VVVV3/5V5/3V7/V4O4V/V3OOO3V/VVO1OKOO1VV/V3OOO3V/V4O4V/11/5V5/3VVVVV3 a"
+        );
+        assert_same_position(&board, &Board::from_notation(&notation).unwrap());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("", NotationError::MissingField("rule set")),
+            ("brandubh", NotationError::MissingField("layout")),
+            (
+                "brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3",
+                NotationError::MissingField("side to move"),
+            ),
+            (
+                "brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a x",
+                NotationError::TrailingInput("x".to_string()),
+            ),
+            (
+                "shogi 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+                NotationError::UnknownRules("shogi".to_string()),
+            ),
+            (
+                "brandubh 3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+                NotationError::RankCount {
+                    expected: 7,
+                    found: 6,
+                },
+            ),
+            (
+                "brandubh 3V3/3V4/3O3/VVOKOVV/3O3/3V3/3V3 a",
+                NotationError::RankLength {
+                    rank: 6,
+                    expected: 7,
+                    found: 8,
+                },
+            ),
+            (
+                "brandubh 99999999999999999999/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+                NotationError::RankLength {
+                    rank: 7,
+                    expected: 7,
+                    found: usize::MAX,
+                },
+            ),
+            (
+                "brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/18446744073709551615V a",
+                NotationError::RankLength {
+                    rank: 1,
+                    expected: 7,
+                    found: usize::MAX,
+                },
+            ),
+            (
+                "brandubh 3V3/3V3/3O3/VVOKOVX/3O3/3V3/3V3 a",
+                NotationError::UnknownPiece('X'),
+            ),
+            (
+                "brandubh V2V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+                NotationError::RestrictedSquare { row: 6, col: 0 },
+            ),
+            (
+                "brandubh 3V3/3V3/3O3/VVO1OVV/3O3/3V3/3V3 a",
+                NotationError::KingCount(0),
+            ),
+            (
+                "brandubh 3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 w",
+                NotationError::UnknownSide("w".to_string()),
+            ),
+        ];
+        for (notation, expected) in cases {
+            assert_eq!(
+                Board::from_notation(notation).err(),
+                Some(expected),
+                "{}",
+                notation
+            );
+        }
+    }
+
+    #[test]
+    fn square_names() {
+        assert_eq!(square_name(rc_to_index(0, 0)), "a1");
+        assert_eq!(square_name(rc_to_index(10, 3)), "d11");
+        assert_eq!(parse_square("d11", 11), Some(rc_to_index(10, 3)));
+        assert_eq!(parse_square("a1", 7), Some(rc_to_index(0, 0)));
+        for bad in [
+            "", "a", "a0", "a01", "h1", "a8", "z9", "A1", "a1x", "1a", "a+1",
+        ] {
+            assert_eq!(parse_square(bad, 7), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn parses_long_moves() {
+        // defenders move first in ard-ri
+        let b = &*STARTING_BOARD;
+        let m = b.parse_move("c3-c2").unwrap();
+        assert_eq!(m.start_index, rc_to_index(2, 2));
+        assert_eq!(m.end_index, rc_to_index(1, 2));
+        assert_eq!(m.piece_type, PieceType::Defender);
+        assert_eq!(b.parse_move("c3c2"), Ok(m));
+
+        let b = Board::new(Rules::fetlar());
+        let m = b.parse_move("d11-d10").unwrap();
+        assert_eq!(m.start_index, rc_to_index(10, 3));
+        assert_eq!(m.end_index, rc_to_index(9, 3));
+        assert_eq!(m.piece_type, PieceType::Attacker);
+    }
+
+    #[test]
+    fn reports_why_a_move_was_rejected() {
+        let b = &*STARTING_BOARD;
+        let cases = [
+            ("", MoveError::Syntax(String::new())),
+            ("c3-", MoveError::Syntax("c3-".to_string())),
+            ("3c2c", MoveError::Syntax("3c2c".to_string())),
+            ("z9c2", MoveError::BadSquare("z9".to_string())),
+            ("c3c8", MoveError::BadSquare("c8".to_string())),
+            ("b2b3", MoveError::EmptySquare("b2".to_string())),
+            ("d2b2", MoveError::NotYourTurn("d2".to_string())),
+            ("Kc3c2", MoveError::NotTheKing("c3".to_string())),
+            (
+                "c3b2",
+                MoveError::Illegal("pieces move along a rank or file"),
+            ),
+            (
+                "c3c1",
+                MoveError::Illegal("pieces move one square at a time"),
+            ),
+            ("c3c4", MoveError::Illegal("the destination is occupied")),
+            ("Kd4d5", MoveError::Illegal("the destination is occupied")),
+            ("c3-c2xc1", MoveError::WrongCaptures("c3-c2".to_string())),
+            ("Kd5", MoveError::NoPieceCanReach("Kd5".to_string())),
+            ("f2", MoveError::NoPieceCanReach("f2".to_string())),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(b.parse_move(s), Err(expected), "{}", s);
+        }
+
+        let b = Board::from_notation("brandubh 3V3/3O3/7/3K3/7/7/7 a").unwrap();
+        assert_eq!(
+            b.parse_move("d7d3"),
+            Err(MoveError::Illegal("the path is blocked"))
+        );
+        assert_eq!(
+            b.parse_move("d7a7"),
+            Err(MoveError::Illegal(
+                "only the king may stop on a restricted square"
+            ))
+        );
+    }
+
+    #[test]
+    fn short_moves_are_disambiguated() {
+        // the attackers on d9, d2 and a6 can all reach d6
+        let b = Board::from_notation("copenhagen 11/9K1/3V7/11/11/V10/11/11/11/3V7/11 a").unwrap();
+        let from = |row, col| Move {
+            start_index: rc_to_index(row, col),
+            end_index: rc_to_index(5, 3),
+            piece_type: PieceType::Attacker,
+        };
+        for (m, short) in [
+            (from(8, 3), "9d6"),
+            (from(1, 3), "2d6"),
+            (from(5, 0), "ad6"),
+        ] {
+            assert_eq!(b.short_move_notation(m), short);
+            assert_eq!(b.parse_move(short), Ok(m));
+        }
+        assert_eq!(
+            b.parse_move("d6"),
+            Err(MoveError::Ambiguous("d6".to_string()))
+        );
+        assert_eq!(
+            b.parse_move("dd6"),
+            Err(MoveError::Ambiguous("dd6".to_string()))
+        );
+
+        // the king has its own prefix, so it never needs disambiguating from other pieces
+        let b = Board::from_notation("brandubh 7/7/7/3K3/7/V6/7 d").unwrap();
+        let m = b.parse_move("Kd2").unwrap();
+        assert_eq!(b.short_move_notation(m), "Kd2");
+        assert_eq!(b.long_move_notation(m), "d4-d2");
+    }
+
+    #[test]
+    fn captures_are_listed() {
+        let b = Board::from_notation("brandubh 7/7/7/3K3/7/1O5/2VO3 d").unwrap();
+        let m = b.parse_move("b2-b1").unwrap();
+        assert_eq!(b.long_move_notation(m), "b2-b1xc1");
+        assert_eq!(b.short_move_notation(m), "b1xc1");
+        assert_eq!(b.parse_move("b1xc1"), Ok(m));
+        assert_eq!(b.parse_move("b2b1xc1"), Ok(m));
+        assert_eq!(b.parse_move("b2-b1x"), Ok(m));
+        assert_eq!(b.parse_move("b1x"), Ok(m));
+        assert_eq!(
+            b.parse_move("b2-b3x"),
+            Err(MoveError::WrongCaptures("b2-b3".to_string()))
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::board::{Board, Move};
+use crate::movegen::MoveGenerator;
+use crate::ttable::TranspositionTable;
+
+/// Number of move sequences of the given length from this position. Games that end early
+/// are not extended.
+pub fn perft(b: &mut Board, depth: usize, tt: &TranspositionTable) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if b.result.is_some() {
+        return 0;
+    }
+    let moves: Vec<Move> = MoveGenerator::new(b).collect();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for m in moves {
+        b.make_move(m, tt);
+        nodes += perft(b, depth - 1, tt);
+        b.unmake_move();
+    }
+    nodes
+}
+
+/// Perft split by root move, in move generator order.
+pub fn divide(b: &mut Board, depth: usize, tt: &TranspositionTable) -> Vec<(Move, u64)> {
+    if depth == 0 || b.result.is_some() {
+        return Vec::new();
+    }
+    let moves: Vec<Move> = MoveGenerator::new(b).collect();
+    moves
+        .into_iter()
+        .map(|m| {
+            b.make_move(m, tt);
+            let nodes = perft(b, depth - 1, tt);
+            b.unmake_move();
+            (m, nodes)
+        })
+        .collect()
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use crate::bitboard::{Bitboard, EMPTY_BOARD};
+use crate::board::{Board, Move};
+use crate::notation::{square_name, MoveError, NotationError};
+use crate::rules::Rules;
+use crate::ttable::TranspositionTable;
+
+// Game records follow OpenTafl: `[key:value]` tag lines, then numbered turns of two moves
+// each, like `1. d1-d3 c4-c2xc3`. Captured squares follow an `x` and are separated by `/`.
+// The rule set and starting position live in the `rules` tag, which holds space separated
+// `key:value` pairs. We read and write `name`, `dim`, `atkf` (attackers move first) and
+// `start`, a layout from the top rank down using `t` for attackers, `T` for defenders and
+// `K` for the king, enclosed in slashes.
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RecordError {
+    BadTag(String),
+    MissingRules,
+    UnknownRules(String),
+    BadRules(String),
+    Position(NotationError),
+    /// A move that can't be read, numbered from one.
+    BadMove {
+        ply: usize,
+        error: MoveError,
+    },
+    /// A move that can't be played in the position reached by the moves before it.
+    IllegalMove {
+        ply: usize,
+        error: MoveError,
+    },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::BadTag(line) => write!(f, "malformed tag: {}", line),
+            RecordError::MissingRules => write!(f, "missing rules tag"),
+            RecordError::UnknownRules(name) => write!(f, "unknown rule set: {}", name),
+            RecordError::BadRules(s) => write!(f, "malformed rules tag: {}", s),
+            RecordError::Position(e) => write!(f, "bad starting position: {}", e),
+            RecordError::BadMove { ply, error } | RecordError::IllegalMove { ply, error } => {
+                write!(f, "move {}: {}", ply, error)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RecordedMove {
+    pub m: Move,
+    pub captured: Bitboard,
+}
+
+#[derive(Clone)]
+pub struct GameRecord {
+    /// Tags other than `rules`, in the order they were read.
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    pub fn new(start: &Board) -> Self {
+        let mut start = start.clone();
+        start.clear_history();
+        start.result = None;
+        GameRecord {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Makes a move on the board and adds it to the record.
+    pub fn play(&mut self, board: &mut Board, m: Move, tt: &TranspositionTable) {
+        board.make_move(m, tt);
+        let captured = board
+            .last_move_info()
+            .map_or(EMPTY_BOARD, |info| info.captured);
+        self.moves.push(RecordedMove { m, captured });
+    }
+
+    /// The position at the end of the record.
+    pub fn replay(&self, tt: &TranspositionTable) -> Board {
+        let mut board = self.start.clone();
+        board.rehash(tt);
+        for rm in &self.moves {
+            board.make_move(rm.m, tt);
+        }
+        board
+    }
+
+    /// Reads a record and checks every move by playing it out.
+    pub fn read(s: &str, tt: &TranspositionTable) -> Result<Self, RecordError> {
+        let mut tags = Vec::new();
+        let mut rules_tag = None;
+        let mut movetext = String::new();
+        for line in s.lines().map(str::trim) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (key, value) = tag
+                    .strip_suffix(']')
+                    .and_then(|t| t.split_once(':'))
+                    .ok_or_else(|| RecordError::BadTag(line.to_string()))?;
+                if key == "rules" {
+                    rules_tag = Some(value.to_string());
+                } else {
+                    tags.push((key.to_string(), value.to_string()));
+                }
+            } else {
+                movetext.push_str(line);
+                movetext.push(' ');
+            }
+        }
+
+        let start = read_rules_tag(&rules_tag.ok_or(RecordError::MissingRules)?)?;
+        let mut record = GameRecord::new(&start);
+        record.tags = tags;
+
+        let mut board = start;
+        board.rehash(tt);
+        let tokens = movetext.split_whitespace().filter(|t| !t.ends_with('.'));
+        for (i, text) in tokens.enumerate() {
+            let ply = i + 1;
+            let m = board.parse_move(text).map_err(|error| match error {
+                MoveError::Syntax(_) | MoveError::BadSquare(_) => {
+                    RecordError::BadMove { ply, error }
+                }
+                _ => RecordError::IllegalMove { ply, error },
+            })?;
+            record.play(&mut board, m, tt);
+        }
+        Ok(record)
+    }
+}
+
+fn read_rules_tag(value: &str) -> Result<Board, RecordError> {
+    let bad = || RecordError::BadRules(value.to_string());
+    let mut name = None;
+    let mut dim = None;
+    let mut atkf = None;
+    let mut start = None;
+    for field in value.split_whitespace() {
+        let (key, v) = field.split_once(':').ok_or_else(bad)?;
+        match key {
+            "name" => name = Some(v),
+            "dim" => dim = Some(v.parse::<usize>().map_err(|_| bad())?),
+            "atkf" => atkf = Some(v == "y"),
+            "start" => start = Some(v),
+            // other OpenTafl rule options are fixed by the named rule set
+            _ => {}
+        }
+    }
+
+    let name = name.ok_or_else(bad)?;
+    let rules =
+        Rules::from_name(name).ok_or_else(|| RecordError::UnknownRules(name.to_string()))?;
+    if dim.is_some_and(|d| d != rules.board_size) {
+        return Err(bad());
+    }
+    // without atkf the side to move is the rule set's, as with the default layout
+    let default_board = Board::new(rules);
+    let mut board = match start {
+        Some(start) => {
+            let layout: String = start
+                .trim_matches('/')
+                .chars()
+                .map(|c| match c {
+                    't' => 'V',
+                    'T' => 'O',
+                    c => c,
+                })
+                .collect();
+            Board::with_position(rules, &format!("{} a", layout)).map_err(RecordError::Position)?
+        }
+        None => default_board.clone(),
+    };
+    board.attacker_move = atkf.unwrap_or(default_board.attacker_move);
+    Ok(board)
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start: String = self
+            .start
+            .layout()
+            .chars()
+            .map(|c| match c {
+                'V' => 't',
+                'O' => 'T',
+                c => c,
+            })
+            .collect();
+        writeln!(
+            f,
+            "[rules:name:{} dim:{} atkf:{} start:/{}/]",
+            self.start.rules.name,
+            self.start.size(),
+            if self.start.attacker_move { 'y' } else { 'n' },
+            start
+        )?;
+        for (key, value) in &self.tags {
+            writeln!(f, "[{}:{}]", key, value)?;
+        }
+
+        for (turn, pair) in self.moves.chunks(2).enumerate() {
+            write!(f, "{}.", turn + 1)?;
+            for rm in pair {
+                write!(f, " {}", rm.m)?;
+                let captured: Vec<String> = rm.captured.into_iter().map(square_name).collect();
+                if !captured.is_empty() {
+                    write!(f, "x{}", captured.join("/"))?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::rc_to_index;
+    use crate::movegen::MoveGenerator;
+
+    const BRANDUBH_GAME: &str = "\
+[rules:name:brandubh dim:7 atkf:y start:/3t3/3t3/3T3/ttTKTtt/3T3/3t3/3t3/]
+[event:test]
+1. d6-c6 e4-e5
+2. c6-c5 e5-e6
+3. d2-c2 e6-f6
+4. c2-c3xc4
+";
+
+    #[test]
+    fn read_and_write_round_trip() {
+        let tt = TranspositionTable::new();
+        let record = GameRecord::read(BRANDUBH_GAME, &tt).unwrap();
+        assert_eq!(record.tags, vec![("event".to_string(), "test".to_string())]);
+        assert_eq!(record.moves.len(), 7);
+        assert_eq!(record.to_string(), BRANDUBH_GAME);
+
+        let board = record.replay(&tt);
+        assert!(!board.attacker_move);
+        assert_eq!(board.current_hash, tt.hash_from_board(&board));
+        assert!(!board.defender_board.contains(rc_to_index(3, 2)));
+    }
+
+    #[test]
+    fn captures_are_filled_in_when_missing() {
+        let tt = TranspositionTable::new();
+        let unannotated = BRANDUBH_GAME.replace("xc4", "");
+        let record = GameRecord::read(&unannotated, &tt).unwrap();
+        assert_eq!(record.to_string(), BRANDUBH_GAME);
+    }
+
+    #[test]
+    fn reports_first_illegal_move() {
+        let tt = TranspositionTable::new();
+        let game = BRANDUBH_GAME.replace("c6-c5", "c6-c4");
+        assert_eq!(
+            GameRecord::read(&game, &tt).err(),
+            Some(RecordError::IllegalMove {
+                ply: 3,
+                error: MoveError::Illegal("the destination is occupied"),
+            })
+        );
+
+        let game = BRANDUBH_GAME.replace("xc4", "xc4/d3");
+        assert!(matches!(
+            GameRecord::read(&game, &tt),
+            Err(RecordError::IllegalMove { ply: 7, .. })
+        ));
+
+        let game = BRANDUBH_GAME.replace("xc4", "q");
+        assert_eq!(
+            GameRecord::read(&game, &tt).err(),
+            Some(RecordError::BadMove {
+                ply: 7,
+                error: MoveError::Syntax("c2-c3q".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn moves_after_the_game_ends_are_illegal() {
+        let tt = TranspositionTable::new();
+        let game = "[rules:name:brandubh start:/7/7/7/3K3/7/6t/7/ atkf:n]\n\
+                    1. d4-d7 g2-g3\n\
+                    2. d7-a7 g3-g4\n";
+        let err = GameRecord::read(game, &tt).err().unwrap();
+        assert_eq!(
+            err,
+            RecordError::IllegalMove {
+                ply: 4,
+                error: MoveError::Illegal("the game is over"),
+            }
+        );
+    }
+
+    #[test]
+    fn default_start_and_written_record_replays() {
+        let tt = TranspositionTable::new();
+        let record = GameRecord::read("[rules:name:copenhagen]\n", &tt).unwrap();
+        let mut board = record.replay(&tt);
+        let mut written = GameRecord::new(&board);
+        for _ in 0..6 {
+            let m = MoveGenerator::new(&board).next().unwrap();
+            written.play(&mut board, m, &tt);
+        }
+        let reread = GameRecord::read(&written.to_string(), &tt).unwrap();
+        assert_eq!(reread.to_string(), written.to_string());
+        assert_eq!(reread.replay(&tt).to_notation(), board.to_notation());
+    }
+
+    #[test]
+    fn missing_atkf_uses_the_rule_set_side() {
+        let tt = TranspositionTable::new();
+        let record = "[rules:name:ard-ri start:/2ttt2/3t3/t1TTT1t/ttTKTtt/t1TTT1t/3t3/2ttt2/]\n";
+        let board = GameRecord::read(record, &tt).unwrap().replay(&tt);
+        assert!(!board.attacker_move);
+        assert_eq!(
+            board.to_notation(),
+            Board::new(Rules::ard_ri()).to_notation()
+        );
+
+        let record =
+            "[rules:name:ard-ri atkf:y start:/2ttt2/3t3/t1TTT1t/ttTKTtt/t1TTT1t/3t3/2ttt2/]\n";
+        assert!(GameRecord::read(record, &tt).unwrap().start.attacker_move);
+        let record = "[rules:name:brandubh start:/3t3/3t3/3T3/ttTKTtt/3T3/3t3/3t3/]\n";
+        assert!(GameRecord::read(record, &tt).unwrap().start.attacker_move);
+    }
+}
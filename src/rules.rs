@@ -0,0 +1,247 @@
+/// How far a piece may travel in a single move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Movement {
+    /// One square orthogonally.
+    Step,
+    /// Any number of empty squares orthogonally, like a rook.
+    Slide,
+}
+
+/// Where the king has to reach for the defenders to win.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Escape {
+    Edge,
+    Corner,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KingCapture {
+    /// Sandwiched between two attackers, like any other piece.
+    TwoSided,
+    /// Surrounded on all four sides. Restricted squares count as attackers.
+    FourSided,
+    /// Surrounded on all four sides on or next to the throne, and sandwiched between two
+    /// attackers anywhere else.
+    ThroneDependent,
+}
+
+/// When the throne acts as an enemy piece in captures.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HostileThrone {
+    Never,
+    /// Hostile to both sides while empty.
+    WhenEmpty,
+    /// Always hostile to attackers, and hostile to defenders while empty.
+    Always,
+}
+
+/// What happens when a move recreates an earlier position with the same side to move. The
+/// count is the number of times the position must have occurred, including the current one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Repetition {
+    /// The game ends in a draw.
+    Draw(usize),
+    /// The side that repeated the position loses.
+    Loss(usize),
+}
+
+/// A complete tafl rule set, including the board size and starting layout.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules {
+    pub name: &'static str,
+    pub board_size: usize,
+    /// Starting layout and side to move, in the notation of `Board::to_notation` without the
+    /// rule set tag.
+    pub start_position: &'static str,
+    pub movement: Movement,
+    pub escape: Escape,
+    /// Whether the corners are restricted to the king, like the throne.
+    pub restricted_corners: bool,
+    /// Whether the king can take part in captures.
+    pub armed_king: bool,
+    pub king_capture: KingCapture,
+    /// Whether the board edge counts as an attacker when surrounding the king.
+    pub king_captured_on_edge: bool,
+    /// Whether the restricted corners act as an enemy piece in captures.
+    pub hostile_corners: bool,
+    pub hostile_throne: HostileThrone,
+    /// Whether a row of pieces along the edge can be captured together.
+    pub shieldwall: bool,
+    /// Whether the defenders win by building an unbreakable fort around the king on the edge.
+    pub edge_fort: bool,
+    /// Whether the attackers win by enclosing the king and all defenders away from the edge.
+    pub encirclement: bool,
+    pub repetition: Repetition,
+    /// Number of consecutive moves without a capture, counting both sides, after which the game
+    /// is drawn.
+    pub move_limit: Option<usize>,
+    /// Whether a side with no legal moves loses. Otherwise the game is drawn.
+    pub no_moves_loses: bool,
+}
+
+impl Rules {
+    /// 7x7 with sixteen attackers and single-step moves. The king escapes to any edge and must
+    /// be surrounded on all four sides, where the edge of the board counts as an attacker.
+    pub const fn ard_ri() -> Self {
+        Rules {
+            name: "ard-ri",
+            board_size: 7,
+            start_position: "2VVV2/3V3/V1OOO1V/VVOKOVV/V1OOO1V/3V3/2VVV2 d",
+            movement: Movement::Step,
+            escape: Escape::Edge,
+            restricted_corners: true,
+            armed_king: true,
+            king_capture: KingCapture::FourSided,
+            king_captured_on_edge: true,
+            hostile_corners: false,
+            hostile_throne: HostileThrone::Never,
+            shieldwall: false,
+            edge_fort: false,
+            encirclement: false,
+            repetition: Repetition::Draw(2),
+            move_limit: None,
+            no_moves_loses: false,
+        }
+    }
+
+    pub const fn brandubh() -> Self {
+        Rules {
+            name: "brandubh",
+            board_size: 7,
+            start_position: "3V3/3V3/3O3/VVOKOVV/3O3/3V3/3V3 a",
+            movement: Movement::Slide,
+            escape: Escape::Corner,
+            restricted_corners: true,
+            armed_king: true,
+            king_capture: KingCapture::TwoSided,
+            king_captured_on_edge: false,
+            hostile_corners: true,
+            hostile_throne: HostileThrone::WhenEmpty,
+            shieldwall: false,
+            edge_fort: false,
+            encirclement: false,
+            repetition: Repetition::Draw(3),
+            move_limit: None,
+            no_moves_loses: true,
+        }
+    }
+
+    pub const fn fetlar() -> Self {
+        Rules {
+            name: "fetlar",
+            board_size: 11,
+            start_position:
+                "3VVVVV3/5V5/11/V4O4V/V3OOO3V/VV1OOKOO1VV/V3OOO3V/V4O4V/11/5V5/3VVVVV3 a",
+            movement: Movement::Slide,
+            escape: Escape::Corner,
+            restricted_corners: true,
+            armed_king: true,
+            king_capture: KingCapture::FourSided,
+            king_captured_on_edge: false,
+            hostile_corners: true,
+            hostile_throne: HostileThrone::Always,
+            shieldwall: false,
+            edge_fort: false,
+            encirclement: false,
+            repetition: Repetition::Draw(3),
+            move_limit: None,
+            no_moves_loses: true,
+        }
+    }
+
+    pub const fn copenhagen() -> Self {
+        Rules {
+            name: "copenhagen",
+            shieldwall: true,
+            edge_fort: true,
+            encirclement: true,
+            repetition: Repetition::Loss(3),
+            ..Rules::fetlar()
+        }
+    }
+
+    pub const fn tablut() -> Self {
+        Rules {
+            name: "tablut",
+            board_size: 9,
+            start_position: "3VVV3/4V4/4O4/V3O3V/VVOOKOOVV/V3O3V/4O4/4V4/3VVV3 a",
+            movement: Movement::Slide,
+            escape: Escape::Edge,
+            restricted_corners: false,
+            armed_king: true,
+            king_capture: KingCapture::ThroneDependent,
+            king_captured_on_edge: false,
+            hostile_corners: false,
+            hostile_throne: HostileThrone::WhenEmpty,
+            shieldwall: false,
+            edge_fort: false,
+            encirclement: false,
+            repetition: Repetition::Draw(3),
+            move_limit: None,
+            no_moves_loses: true,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ard-ri" => Some(Rules::ard_ri()),
+            "brandubh" => Some(Rules::brandubh()),
+            "fetlar" => Some(Rules::fetlar()),
+            "copenhagen" => Some(Rules::copenhagen()),
+            "tablut" => Some(Rules::tablut()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::MAX_BOARD_SIZE;
+    use crate::board::Board;
+
+    // Every preset with its size, movement, number of escape squares, attackers and defenders
+    // (not counting the king), and whether the attackers move first.
+    const PRESETS: [(Rules, usize, Movement, u32, u32, u32, bool); 5] = [
+        (Rules::ard_ri(), 7, Movement::Step, 24, 16, 8, false),
+        (Rules::brandubh(), 7, Movement::Slide, 4, 8, 4, true),
+        (Rules::fetlar(), 11, Movement::Slide, 4, 24, 12, true),
+        (Rules::copenhagen(), 11, Movement::Slide, 4, 24, 12, true),
+        (Rules::tablut(), 9, Movement::Slide, 32, 16, 8, true),
+    ];
+
+    #[test]
+    fn presets_set_up_their_boards() {
+        for (rules, size, movement, escapes, attackers, defenders, attacker_move) in PRESETS {
+            let b = Board::new(rules);
+            assert_eq!(rules.board_size, size, "{}", rules.name);
+            assert_eq!(b.size(), size, "{}", rules.name);
+            assert_eq!(rules.movement, movement, "{}", rules.name);
+            assert_eq!(b.escape_board().count_ones(), escapes, "{}", rules.name);
+            assert_eq!(b.attacker_board.count_ones(), attackers, "{}", rules.name);
+            assert_eq!(b.defender_board.count_ones(), defenders, "{}", rules.name);
+            assert_eq!(b.king_board.count_ones(), 1, "{}", rules.name);
+            assert_eq!(b.attacker_move, attacker_move, "{}", rules.name);
+        }
+    }
+
+    #[test]
+    fn corner_escapes_are_the_corners() {
+        let b = Board::new(Rules::brandubh());
+        let n = b.size();
+        for (row, col) in [(0, 0), (0, n - 1), (n - 1, 0), (n - 1, n - 1)] {
+            assert!(b.escape_board().contains(row * MAX_BOARD_SIZE + col));
+        }
+        assert!(!b.escape_board().contains(3));
+    }
+
+    #[test]
+    fn presets_are_found_by_name() {
+        for (rules, ..) in PRESETS {
+            assert_eq!(Rules::from_name(rules.name), Some(rules));
+        }
+        assert_eq!(Rules::from_name("Brandubh"), Some(Rules::brandubh()));
+        assert_eq!(Rules::from_name("hnefatafl"), None);
+        assert_eq!(Rules::from_name(""), None);
+    }
+}
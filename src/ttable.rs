@@ -1,14 +1,14 @@
 use rand::Rng;
 
-use crate::board::{Bitboard, Board, BOARD_SIZE};
+use crate::bitboard::{Bitboard, NUM_SQUARES};
+use crate::board::Board;
 const TTABLE_SIZE: usize = 1 << 20;
-const NUM_SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum Flag {
-    EXACT,
-    LOWERBOUND,
-    UPPERBOUND,
+    Exact,
+    LowerBound,
+    UpperBound,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -30,27 +30,50 @@ pub struct TranspositionTable {
     pub capacity: usize,
 }
 
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TranspositionTable {
     pub fn new() -> Self {
+        Self::with_capacity(TTABLE_SIZE)
+    }
+
+    /// The largest table that fits in the given number of megabytes, with at least one entry.
+    pub fn with_size_mb(mb: usize) -> Self {
+        let entries = mb.saturating_mul(1 << 20) / std::mem::size_of::<TranspositionTableEntry>();
+        // capacity must be a power of two so that keys can be masked into an index
+        let capacity = if entries <= 1 {
+            1
+        } else {
+            1 << entries.ilog2()
+        };
+        Self::with_capacity(capacity)
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
         let init_entry = TranspositionTableEntry {
             evaluation: 0,
             depth: 0,
             key: 0,
-            flag: Flag::EXACT,
+            flag: Flag::Exact,
         };
-        return TranspositionTable {
-            table: vec![init_entry; TTABLE_SIZE],
+        TranspositionTable {
+            table: vec![init_entry; capacity],
             init_hash: make_init_hash(),
             attacker_bits_seed: rand::thread_rng().gen::<usize>(),
-            capacity: TTABLE_SIZE,
-        };
+            capacity,
+        }
     }
 
-    #[inline]
-    pub fn haskey(&self, b: &Board) -> bool {
-        let hash = b.current_hash;
-        let index = hash & (self.capacity - 1);
-        self.table[index].key == hash
+    /// Forgets every stored entry, keeping the hash keys.
+    pub fn clear(&mut self) {
+        for entry in self.table.iter_mut() {
+            entry.key = 0;
+            entry.depth = 0;
+        }
     }
 
     #[inline]
@@ -89,11 +112,8 @@ fn update_hash_with_board(
     init_board: &[[usize; 3]; NUM_SQUARES],
     piece_type_idx: usize,
 ) {
-    let mut bb = board;
-    while bb != 0 {
-        let idx = bb.trailing_zeros() as usize;
+    for idx in board {
         *h ^= init_board[idx][piece_type_idx];
-        bb &= bb - 1;
     }
 }
 
@@ -121,9 +141,9 @@ fn zobrist_hash(b: &Board, attacker_bits: usize, init_board: &[[usize; 3]; NUM_S
 fn make_init_hash() -> [[usize; 3]; NUM_SQUARES] {
     let mut init_board = [[0; 3]; NUM_SQUARES];
     let mut rng = rand::thread_rng();
-    for i in 0..NUM_SQUARES {
-        for j in 0..3 {
-            init_board[i][j] = rng.gen::<usize>();
+    for square in init_board.iter_mut() {
+        for seed in square.iter_mut() {
+            *seed = rng.gen::<usize>();
         }
     }
 
@@ -1,142 +1,119 @@
-use std::io::{Read, Write};
+use std::io::Write;
 
-use crate::board::{rc_to_index, Board, Move, PieceType};
-use crate::engine::EngineBenchmark;
+use crate::board::{Board, Move, Outcome};
+use crate::engine::{EngineBenchmark, SearchInfo};
 
 pub trait UI {
-    fn get_move(&mut self, b: &Board) -> Move;
+    /// The next legal move for the side to move, or `None` once input runs out.
+    fn get_move(&mut self, b: &Board) -> Option<Move>;
     fn render_board(&self, b: &Board);
-    fn render_eval(&self, benchmark: &EngineBenchmark);
-    fn invalid_move(&self);
-    fn attacker_win(&self);
-    fn defender_win(&self);
-    fn stalemate(&self);
+    fn render_eval(&self, b: &Board, benchmark: &EngineBenchmark);
+    /// Shows the progress of a search of `b` that is still running.
+    fn render_info(&self, b: &Board, info: &SearchInfo);
+    /// Announces a move about to be played on `b`, chosen by the engine or by this UI.
+    fn render_move(&self, b: &Board, m: Move, by_engine: bool);
+    fn game_over(&self, outcome: Outcome);
 }
 
 pub struct ConsoleUI {
     stdin: std::io::Stdin,
 }
 
+impl Default for ConsoleUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConsoleUI {
     pub fn new() -> ConsoleUI {
-        return ConsoleUI {
+        ConsoleUI {
             stdin: std::io::stdin(),
-        };
+        }
     }
 }
 
 impl UI for ConsoleUI {
-    fn get_move(&mut self, b: &Board) -> Move {
-        print!("Make a move: ");
-        std::io::stdout().flush().unwrap();
-        let mut buf = [0; 10];
-        let t = self.stdin.read(&mut buf);
-
-        match t {
-            Ok(n) => {
-                let mut move_str = String::new();
-                for i in 0..n {
-                    move_str.push(buf[i] as char);
-                }
-                let m = parse_move(&move_str, b.attacker_move);
-                match m {
-                    Some(mv) => {
-                        return mv;
-                    }
-                    None => {
-                        print!("Unable to parse move!\n");
-                        return self.get_move(b);
-                    }
-                }
+    fn get_move(&mut self, b: &Board) -> Option<Move> {
+        loop {
+            print!("Make a move: ");
+            std::io::stdout().flush().unwrap();
+            let mut line = String::new();
+            match self.stdin.read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
             }
-            Err(_) => {
-                print!("Unable to parse move!\n");
-                return self.get_move(b);
+            match b.parse_move(line.trim()) {
+                Ok(m) => return Some(m),
+                Err(e) => println!("{}", e),
             }
         }
     }
 
     fn render_board(&self, b: &Board) {
-        println!("{}", b.to_string());
+        println!("{}", b);
+        println!("{}", b.to_notation());
     }
 
-    fn render_eval(&self, benchmark: &EngineBenchmark) {
-        println!(
-            "Recommended Move: {}",
-            benchmark.recommendation.best_move.to_string()
-        );
+    fn render_eval(&self, b: &Board, benchmark: &EngineBenchmark) {
+        let best_move = benchmark.recommendation.best_move;
+        println!("Recommended Move: {}", b.short_move_notation(best_move));
+        println!("Expected line: {}", line(&benchmark.recommendation.pv));
         let eval_normalized = benchmark.recommendation.evaluation as f64 / 100.0;
         let sgn = if eval_normalized >= 0.0 { "+" } else { "" };
         println!(
-            "Evaluation: {}{:.2?} ({} nodes) ({:.2?})",
-            sgn, eval_normalized, benchmark.recommendation.nnodes, benchmark.elapsed
+            "Evaluation: {}{:.2?} (depth {}, {} nodes) ({:.2?})",
+            sgn,
+            eval_normalized,
+            benchmark.recommendation.depth,
+            benchmark.recommendation.nnodes,
+            benchmark.elapsed
         );
+        if benchmark.recommendation.lines.len() > 1 {
+            for (i, pv_line) in benchmark.recommendation.lines.iter().enumerate() {
+                println!(
+                    "{}. {} {:+.2} {}",
+                    i + 1,
+                    b.short_move_notation(pv_line.m),
+                    pv_line.evaluation as f64 / 100.0,
+                    line(&pv_line.pv)
+                );
+            }
+        }
     }
 
-    fn invalid_move(&self) {
-        println!("Invalid Move!");
-    }
-
-    fn attacker_win(&self) {
-        println!("Attacker Wins!");
-    }
-
-    fn defender_win(&self) {
-        println!("Defender Wins!");
-    }
-
-    fn stalemate(&self) {
-        println!("Stalemate!");
-    }
-}
-fn validate_move_num(c: Option<char>, sub_val: usize) -> Option<usize> {
-    c.and_then(|x| {
-        let value = x as usize;
-        if value >= sub_val {
-            Some(value - sub_val)
-        } else {
-            None
+    fn render_info(&self, _: &Board, info: &SearchInfo) {
+        for (i, pv_line) in info.lines.iter().enumerate() {
+            let multi_pv = if info.lines.len() > 1 {
+                format!(" multipv {}", i + 1)
+            } else {
+                String::new()
+            };
+            println!(
+                "depth {}{} score {:+.2} nodes {} nps {} time {:.2?} pv {}",
+                info.depth,
+                multi_pv,
+                pv_line.evaluation as f64 / 100.0,
+                info.nodes,
+                info.nps,
+                info.elapsed,
+                line(&pv_line.pv)
+            );
         }
-    })
-}
-
-fn parse_num(c: &mut std::str::Chars, dir: char) -> Option<usize> {
-    if let Some(ch) = c.next() {
-        validate_move_num(Some(ch), dir as usize)
-    } else {
-        None
     }
-}
-
-pub fn parse_move(s: &String, attacker_move: bool) -> Option<Move> {
-    let mut m = Move {
-        start_index: 0,
-        end_index: 0,
-        piece_type: PieceType::Defender,
-    };
 
-    let mut c = s.chars();
-    if c.clone().nth(0) == Some('k') {
-        m.piece_type = PieceType::King;
-        c.next(); // Consume 'k'
+    fn render_move(&self, b: &Board, m: Move, by_engine: bool) {
+        let who = if by_engine { "Engine played" } else { "Played" };
+        println!("{} {}", who, b.long_move_notation(m));
     }
 
-    if attacker_move {
-        m.piece_type = PieceType::Attacker;
+    fn game_over(&self, outcome: Outcome) {
+        println!("{}!", outcome);
     }
+}
 
-    let end_row: usize;
-    let end_col: usize;
-    let start_row: usize;
-    let start_col: usize;
-
-    start_col = parse_num(&mut c, 'a')?;
-    start_row = parse_num(&mut c, '1')?;
-    end_col = parse_num(&mut c, 'a')?;
-    end_row = parse_num(&mut c, '1')?;
-
-    m.start_index = rc_to_index(start_row, start_col);
-    m.end_index = rc_to_index(end_row, end_col);
-
-    Some(m)
+// Moves in long notation, without captures since those depend on the moves before them.
+fn line(moves: &[Move]) -> String {
+    let moves: Vec<String> = moves.iter().map(Move::to_string).collect();
+    moves.join(" ")
 }
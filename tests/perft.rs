@@ -0,0 +1,83 @@
+use tafl_rust::perft::{divide, perft};
+use tafl_rust::{Board, Rules, TranspositionTable};
+
+// Node counts from the start position of each rule set, starting at depth 1. These come from
+// this engine, so they only guard against changes in move generation. The depth 1 counts can be
+// checked by hand: in brandubh each of the four arms of attackers has 4 moves from the outer
+// piece and 6 from the inner one, in tablut each group of four has 6 + 6 + 8 and in fetlar each
+// group of six has 6 + 6 + 3 + 3 + 11. Fetlar and copenhagen share a layout and movement and differ
+// only in shieldwalls, edge forts, encirclement and repetition losses, none of which can happen
+// within three plies of the start, so their counts agree.
+const KNOWN_PERFT: [(&str, &[u64]); 5] = [
+    ("ard-ri", &[8, 176, 1736, 38400]),
+    ("brandubh", &[40, 960, 39512]),
+    ("tablut", &[80, 4400, 353200]),
+    ("fetlar", &[116, 6788, 806344]),
+    ("copenhagen", &[116, 6788, 806344]),
+];
+
+// Small positions counted by hand, with the reasoning next to each.
+const HAND_COUNTED_PERFT: [(&str, &[u64]); 4] = [
+    // The attacker on b1 has 4 moves along the first rank, where it can't stop on either
+    // corner, and 6 up the b file. The king then has 12 moves from the throne, less 1 when the
+    // attacker is on d1 and 2 when it's on b4: 8 * 12 + 11 + 10.
+    ("brandubh 7/7/7/3K3/7/7/1V5 a", &[10, 117]),
+    // The king steps to one of 4 squares and the attacker on d1 to one of 3. The king then has
+    // 4 steps again, apart from d3 after the attacker went to d2: 3 * 12 + 11.
+    ("ard-ri 7/7/7/3K3/7/7/3V3 d", &[4, 12, 47]),
+    // Tablut leaves the corners open, so the attacker on a1 has 8 moves along each edge. The
+    // king then has 16 moves from the throne, less 1 when the attacker is on e1 or a5:
+    // 14 * 16 + 2 * 15.
+    ("tablut 9/9/9/9/4K4/9/9/9/V8 a", &[16, 254]),
+    // The attacker on b1 has 8 moves along the first rank between the corners and 10 up the b
+    // file. The king then has 20 moves from the throne, less 1 when the attacker is on f1 and 2
+    // when it's on b6: 16 * 20 + 19 + 18.
+    ("fetlar 11/11/11/11/11/5K5/11/11/11/11/1V9 a", &[18, 357]),
+];
+
+#[test]
+fn known_perft_values() {
+    let tt = TranspositionTable::new();
+    for (name, counts) in KNOWN_PERFT {
+        let mut b = Board::new(Rules::from_name(name).unwrap());
+        b.rehash(&tt);
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(
+                perft(&mut b, depth + 1, &tt),
+                count,
+                "{} depth {}",
+                name,
+                depth + 1
+            );
+        }
+        assert!(b.history.is_empty());
+    }
+}
+
+#[test]
+fn hand_counted_perft_values() {
+    let tt = TranspositionTable::new();
+    for (notation, counts) in HAND_COUNTED_PERFT {
+        let mut b = Board::from_notation(notation).unwrap();
+        b.rehash(&tt);
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(
+                perft(&mut b, depth + 1, &tt),
+                count,
+                "{} depth {}",
+                notation,
+                depth + 1
+            );
+        }
+    }
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let tt = TranspositionTable::new();
+    let mut b = Board::new(Rules::from_name("brandubh").unwrap());
+    b.rehash(&tt);
+    let split = divide(&mut b, 3, &tt);
+    assert_eq!(split.len(), 40);
+    assert_eq!(split.iter().map(|&(_, n)| n).sum::<u64>(), 39512);
+}
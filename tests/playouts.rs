@@ -0,0 +1,117 @@
+// Random games checking that make_move and unmake_move keep the board consistent.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use tafl_rust::bitboard::EMPTY_BOARD;
+use tafl_rust::board::STARTING_BOARD;
+use tafl_rust::movegen::{escape_routes, MoveGenerator};
+use tafl_rust::{Board, Outcome, PieceType, Reason, Rules, TranspositionTable};
+
+const MAX_PLIES: usize = 300;
+
+fn check_board(b: &Board, tt: &TranspositionTable) {
+    assert_eq!(b.current_hash, tt.hash_from_board(b), "{}", b.to_notation());
+    assert!((b.attacker_board & b.defender_board).is_empty());
+    assert!((b.attacker_board & b.king_board).is_empty());
+    assert!((b.defender_board & b.king_board).is_empty());
+    assert_eq!(b.king_board.count_ones(), 1);
+
+    let pieces = b.attacker_board | b.defender_board | b.king_board;
+    assert_eq!(pieces & !b.geometry.squares, EMPTY_BOARD);
+    // only the king may stand on a restricted square
+    assert!(((b.attacker_board | b.defender_board) & b.offlimits_board).is_empty());
+}
+
+fn random_games(start: &Board, games: usize, seed: u64) {
+    let tt = TranspositionTable::new();
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..games {
+        let mut b = start.clone();
+        b.rehash(&tt);
+        let mut positions = Vec::new();
+
+        while b.outcome().is_none() && positions.len() < MAX_PLIES {
+            let moves: Vec<_> = MoveGenerator::new(&b).collect();
+            let m = *moves.choose(&mut rng).unwrap();
+            positions.push((b.to_notation(), b.current_hash));
+            b.make_move(m, &tt);
+            check_board(&b, &tt);
+        }
+
+        while let Some((notation, hash)) = positions.pop() {
+            b.unmake_move();
+            check_board(&b, &tt);
+            assert_eq!(b.current_hash, hash);
+            assert_eq!(b.to_notation(), notation);
+            assert_eq!(b.result, None);
+        }
+        assert!(b.history.is_empty());
+    }
+}
+
+#[test]
+fn random_games_from_starting_board() {
+    random_games(&STARTING_BOARD, 1000, 1);
+}
+
+#[test]
+fn random_games_for_every_rule_set() {
+    for (seed, name) in ["brandubh", "tablut", "fetlar", "copenhagen"]
+        .into_iter()
+        .enumerate()
+    {
+        let rules = Rules::from_name(name).unwrap();
+        random_games(&Board::new(rules), 100, seed as u64);
+    }
+}
+
+// The captures and escape threat generators pick out exactly the moves that capture or
+// threaten once made.
+fn check_generators(b: &mut Board, tt: &TranspositionTable) {
+    let mut captures: Vec<_> = MoveGenerator::captures(b).collect();
+    let mut threats: Vec<_> = MoveGenerator::escape_threats(b).collect();
+    for m in MoveGenerator::new(b) {
+        b.make_move(m, tt);
+        let info = b.last_move_info().unwrap();
+        let captures_something = !info.captured.is_empty()
+            || info.result == Some(Outcome::AttackersWin(Reason::KingCaptured));
+        let threatens = m.piece_type == PieceType::King
+            && (info.result == Some(Outcome::DefendersWin(Reason::KingEscaped))
+                || !escape_routes(b).is_empty());
+        b.unmake_move();
+
+        let listed = captures.iter().position(|&c| c == m);
+        assert_eq!(
+            listed.is_some(),
+            captures_something,
+            "{} {}",
+            b.to_notation(),
+            m
+        );
+        listed.map(|i| captures.swap_remove(i));
+        let listed = threats.iter().position(|&t| t == m);
+        assert_eq!(listed.is_some(), threatens, "{} {}", b.to_notation(), m);
+        listed.map(|i| threats.swap_remove(i));
+    }
+    assert!(captures.is_empty() && threats.is_empty());
+}
+
+#[test]
+fn tactical_generators_match_made_moves() {
+    let tt = TranspositionTable::new();
+    let mut rng = StdRng::seed_from_u64(7);
+    for name in ["ard-ri", "brandubh", "tablut", "fetlar", "copenhagen"] {
+        let rules = Rules::from_name(name).unwrap();
+        for _ in 0..20 {
+            let mut b = Board::new(rules);
+            b.rehash(&tt);
+            while b.outcome().is_none() && b.history.len() < MAX_PLIES {
+                check_generators(&mut b, &tt);
+                let moves: Vec<_> = MoveGenerator::new(&b).collect();
+                b.make_move(*moves.choose(&mut rng).unwrap(), &tt);
+            }
+        }
+    }
+}